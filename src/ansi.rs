@@ -4,7 +4,7 @@
 //! that can be used to write colored output to terminals that support ANSI
 //! color codes.
 
-use crate::{Color, ColorSpec};
+use crate::{Attribute, Color, ColorSpec};
 use std::fmt;
 use std::io;
 
@@ -13,6 +13,9 @@ use std::io;
 /// If `reset` is true, then the reset escape sequence will be written before
 /// any color escape codes.
 ///
+/// Underline styles are always written as given. Unlike `AnsiWriter`, this
+/// never falls back to a plain underline.
+///
 /// The caller must provide their own `IoWrite` to write to. Callers should
 /// prefer higher level types in this crate, such as `StandardStream` or
 /// `Buffer`.
@@ -23,33 +26,220 @@ pub fn ansi_spec<W: io::Write>(
     if spec.reset() {
         write!(wtr, "\x1B[0m")?;
     }
-    if spec.bold() {
-        write!(wtr, "\x1B[1m")?;
-    }
-    if spec.dimmed() {
-        write!(wtr, "\x1B[2m")?;
-    }
-    if spec.italic() {
-        write!(wtr, "\x1B[3m")?;
-    }
-    if spec.underline() {
-        write!(wtr, "\x1B[4m")?;
-    }
-    if spec.strikethrough() {
-        write!(wtr, "\x1B[9m")?;
-    }
+    ansi_attrs(&mut wtr, spec, true)?;
     if let Some(c) = spec.fg() {
         ansi_color(&mut wtr, c, false)?;
     }
     if let Some(c) = spec.bg() {
         ansi_color(&mut wtr, c, true)?;
     }
+    if let Some(c) = spec.underline_color() {
+        ansi_underline_color(&mut wtr, c)?;
+    }
     if spec.intense() && spec.fg().is_some() {
         write!(wtr, "\x1B[1m")?;
     }
     Ok(())
 }
 
+/// Writes the shortest SGR sequence that changes the terminal from the
+/// state set by `from` to the state set by `to`.
+///
+/// Both specifications are treated as complete terminal states, as if they
+/// were applied with reset enabled: unset colors are the terminal's default
+/// colors, and unset attributes are off. Whether `reset` is enabled is
+/// ignored. As with `ansi_spec`, intense colors are written as bold text.
+///
+/// Attributes that are no longer set are turned off with their SGR off code
+/// and colors that are no longer set are reset with the codes 39, 49 and 59,
+/// unless a full reset followed by all of the settings of `to` is shorter.
+/// All codes are combined into a single escape sequence, and nothing is
+/// written if both states are the same.
+///
+/// The caller must provide their own `IoWrite` to write to. Callers should
+/// prefer higher level types in this crate, such as `StandardStream` or
+/// `Buffer`. See `AnsiWriter::set_minimal_transitions` to have a writer track
+/// its state and always write transitions.
+pub fn ansi_transition<W: io::Write>(
+    wtr: W,
+    from: &ColorSpec,
+    to: &ColorSpec,
+) -> io::Result<()> {
+    let state = |spec: &ColorSpec| {
+        let mut state = *spec;
+        if state.intense() && state.fg().is_some() {
+            state.set_bold(true);
+        }
+        normalize_state(&mut state);
+        state
+    };
+    write_transition(wtr, Some(&state(from)), &state(to))
+}
+
+/// Turns a color specification into the terminal state that it sets, by
+/// unsetting every attribute that's turned off.
+///
+/// The result has reset enabled and intensity disabled, which callers must
+/// account for first.
+pub(crate) fn normalize_state(spec: &mut ColorSpec) {
+    for attr in Attribute::ALL {
+        if spec.attr(attr) == Some(false) {
+            spec.set_attr(attr, None);
+        }
+    }
+    spec.set_intense(false);
+    spec.set_reset(true);
+}
+
+/// Writes the SGR sequence that changes the terminal from state `from` to
+/// state `to`, where both states are normalized with `normalize_state`.
+///
+/// If `from` is `None`, then the current state is unknown and the sequence
+/// starts with a full reset.
+pub(crate) fn write_transition<W: io::Write>(
+    mut wtr: W,
+    from: Option<&ColorSpec>,
+    to: &ColorSpec,
+) -> io::Result<()> {
+    let mut full = vec!["0".to_string()];
+    full.extend(state_params(to));
+    let params = match from {
+        None => full,
+        Some(from) => {
+            let changes = transition_params(from, to);
+            let len = |params: &[String]| {
+                params.iter().map(|p| p.len() + 1).sum::<usize>()
+            };
+            if len(&changes) <= len(&full) { changes } else { full }
+        }
+    };
+    if params.is_empty() {
+        return Ok(());
+    }
+    write!(wtr, "\x1B[{}m", params.join(";"))
+}
+
+/// Returns the SGR parameters that set up the given state after a reset.
+fn state_params(to: &ColorSpec) -> Vec<String> {
+    let mut params = vec![];
+    for attr in Attribute::ALL {
+        if to.attr(attr) == Some(true) {
+            params.push(on_param(to, attr));
+        }
+    }
+    let colors = [(38, to.fg()), (48, to.bg()), (58, to.underline_color())];
+    for (layer, color) in colors {
+        if let Some(c) = color {
+            params.push(color_param(c, layer));
+        }
+    }
+    params
+}
+
+/// Returns the SGR parameters that change state `from` into state `to`
+/// without a reset.
+fn transition_params(from: &ColorSpec, to: &ColorSpec) -> Vec<String> {
+    let mut params = vec![];
+    let mut offs = vec![];
+    for attr in Attribute::ALL {
+        let code = attr.off_sgr();
+        if from.attr(attr).is_some()
+            && to.attr(attr).is_none()
+            && !offs.contains(&code)
+        {
+            offs.push(code);
+            params.push(code.to_string());
+        }
+    }
+    for attr in Attribute::ALL {
+        if to.attr(attr).is_none() {
+            continue;
+        }
+        // An attribute must be written again if it was turned off along
+        // with another one that shares its off code.
+        if from.attr(attr).is_none()
+            || offs.contains(&attr.off_sgr())
+            || (attr == Attribute::Underline
+                && from.underline_style() != to.underline_style())
+        {
+            params.push(on_param(to, attr));
+        }
+    }
+    let colors = [
+        (38, from.fg(), to.fg()),
+        (48, from.bg(), to.bg()),
+        (58, from.underline_color(), to.underline_color()),
+    ];
+    for (layer, old, new) in colors {
+        if old != new {
+            params.push(color_param(new.unwrap_or(&Color::Default), layer));
+        }
+    }
+    params
+}
+
+/// Returns the SGR parameter that turns on an attribute of the given state.
+fn on_param(state: &ColorSpec, attr: Attribute) -> String {
+    match state.underline_style() {
+        Some(style) if attr == Attribute::Underline => style.sgr().to_string(),
+        _ => attr.on_sgr().to_string(),
+    }
+}
+
+/// Returns the SGR parameters that set a color, where `layer` is 38 for the
+/// foreground, 48 for the background or 58 for the underline.
+fn color_param(color: &Color, layer: u8) -> String {
+    match *color {
+        Color::Ansi256(n) => format!("{layer};5;{n}"),
+        Color::Rgb(r, g, b) => format!("{layer};2;{r};{g};{b}"),
+        Color::Default => format!("{}", layer + 1),
+        c => {
+            let n = c.ansi16_index().expect("a named color");
+            match (layer, n) {
+                (58, n) => format!("58;5;{n}"),
+                (_, 0..=7) => format!("{}", layer - 8 + n),
+                (_, n) => format!("{}", layer + 52 + (n - 8)),
+            }
+        }
+    }
+}
+
+/// Writes the SGR sequences for the attributes of the given color
+/// specification.
+///
+/// Attributes that are turned off are written first, since some of them
+/// share an off code, e.g., turning bold off also turns dimmed text off. If
+/// `styled_underline` is false, then every underline style is written as a
+/// plain underline.
+pub(crate) fn ansi_attrs<W: io::Write>(
+    mut wtr: W,
+    spec: &ColorSpec,
+    styled_underline: bool,
+) -> io::Result<()> {
+    let mut last_off = None;
+    for attr in Attribute::ALL {
+        let code = attr.off_sgr();
+        if spec.attr(attr) == Some(false) && last_off != Some(code) {
+            write!(wtr, "\x1B[{code}m")?;
+            last_off = Some(code);
+        }
+    }
+    for attr in Attribute::ALL {
+        if spec.attr(attr) != Some(true) {
+            continue;
+        }
+        match spec.underline_style() {
+            Some(style)
+                if attr == Attribute::Underline && styled_underline =>
+            {
+                write!(wtr, "\x1B[{}m", style.sgr())?;
+            }
+            _ => write!(wtr, "\x1B[{}m", attr.on_sgr())?,
+        }
+    }
+    Ok(())
+}
+
 /// Writes an ANSI escape sequence corresponding to the given color.
 ///
 /// If `bg` is true, then the color is treated as a background color.
@@ -120,6 +310,62 @@ pub fn ansi_color<W: io::Write>(
                 write!(wtr, "\x1B[37m")
             }
         }
+        Color::BrightBlack => {
+            if bg {
+                write!(wtr, "\x1B[100m")
+            } else {
+                write!(wtr, "\x1B[90m")
+            }
+        }
+        Color::BrightBlue => {
+            if bg {
+                write!(wtr, "\x1B[104m")
+            } else {
+                write!(wtr, "\x1B[94m")
+            }
+        }
+        Color::BrightGreen => {
+            if bg {
+                write!(wtr, "\x1B[102m")
+            } else {
+                write!(wtr, "\x1B[92m")
+            }
+        }
+        Color::BrightRed => {
+            if bg {
+                write!(wtr, "\x1B[101m")
+            } else {
+                write!(wtr, "\x1B[91m")
+            }
+        }
+        Color::BrightCyan => {
+            if bg {
+                write!(wtr, "\x1B[106m")
+            } else {
+                write!(wtr, "\x1B[96m")
+            }
+        }
+        Color::BrightMagenta => {
+            if bg {
+                write!(wtr, "\x1B[105m")
+            } else {
+                write!(wtr, "\x1B[95m")
+            }
+        }
+        Color::BrightYellow => {
+            if bg {
+                write!(wtr, "\x1B[103m")
+            } else {
+                write!(wtr, "\x1B[93m")
+            }
+        }
+        Color::BrightWhite => {
+            if bg {
+                write!(wtr, "\x1B[107m")
+            } else {
+                write!(wtr, "\x1B[97m")
+            }
+        }
         Color::Ansi256(n) => {
             if bg {
                 write!(wtr, "\x1B[48;5;{n}m")
@@ -134,6 +380,33 @@ pub fn ansi_color<W: io::Write>(
                 write!(wtr, "\x1B[38;2;{r};{g};{b}m")
             }
         }
+        Color::Default => {
+            if bg {
+                write!(wtr, "\x1B[49m")
+            } else {
+                write!(wtr, "\x1B[39m")
+            }
+        }
+    }
+}
+
+/// Writes the SGR sequence that sets the underline color.
+///
+/// Named colors are written as their index in the 256 color palette, since
+/// there are no dedicated codes for them. So they're written the same as
+/// the equivalent `Ansi256` colors.
+pub(crate) fn ansi_underline_color<W: io::Write>(
+    mut wtr: W,
+    color: &Color,
+) -> io::Result<()> {
+    match *color {
+        Color::Ansi256(n) => write!(wtr, "\x1B[58;5;{n}m"),
+        Color::Rgb(r, g, b) => write!(wtr, "\x1B[58;2;{r};{g};{b}m"),
+        Color::Default => write!(wtr, "\x1B[59m"),
+        c => {
+            let n = c.ansi16_index().expect("a named color");
+            write!(wtr, "\x1B[58;5;{n}m")
+        }
     }
 }
 
@@ -166,3 +439,121 @@ impl fmt::Display for AnsiColor {
         write!(f, "{}", String::from_utf8_lossy(&buf))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnsiWriter, UnderlineStyle, WriteColor};
+
+    /// Returns a deterministic assortment of color specifications, with
+    /// attributes that are on, off or unset and a mix of color kinds.
+    fn specs(count: usize) -> Vec<ColorSpec> {
+        let colors = [
+            None,
+            Some(Color::Red),
+            Some(Color::Ansi256(9)),
+            Some(Color::Ansi256(208)),
+            Some(Color::Rgb(255, 136, 0)),
+            Some(Color::Default),
+        ];
+        let styles = [UnderlineStyle::Single, UnderlineStyle::Curly];
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        let mut next = |n: usize| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % n as u64) as usize
+        };
+        (0..count)
+            .map(|_| {
+                let mut spec = ColorSpec::new();
+                for attr in Attribute::ALL {
+                    spec.set_attr(
+                        attr,
+                        [None, Some(true), Some(false)][next(3)],
+                    );
+                }
+                if spec.underline() {
+                    spec.set_underline_style(Some(styles[next(2)]));
+                }
+                spec.set_fg(colors[next(colors.len())])
+                    .set_bg(colors[next(colors.len())])
+                    .set_underline_color(colors[next(colors.len())]);
+                spec
+            })
+            .collect()
+    }
+
+    /// Returns the terminal state that the given SGR sequences leave
+    /// behind, in the form of `normalize_state`.
+    fn terminal(sgr: &[u8]) -> ColorSpec {
+        let mut state = ColorSpec::from_sgr(sgr).expect("valid SGR");
+        if state.intense() {
+            state.set_bold(true);
+        }
+        let default =
+            |c: Option<&Color>| c.copied().filter(|&c| c != Color::Default);
+        let (fg, bg, ul) = (
+            default(state.fg()),
+            default(state.bg()),
+            default(state.underline_color()),
+        );
+        state.set_fg(fg).set_bg(bg).set_underline_color(ul);
+        normalize_state(&mut state);
+        state
+    }
+
+    fn spec_sgr(spec: &ColorSpec) -> Vec<u8> {
+        let mut buf = vec![];
+        ansi_spec(&mut buf, spec).unwrap();
+        buf
+    }
+
+    #[test]
+    fn transition_matches_full_spec() {
+        let specs = specs(40);
+        for from in &specs {
+            for to in &specs {
+                let mut sgr = spec_sgr(from);
+                ansi_transition(&mut sgr, from, to).unwrap();
+                assert_eq!(
+                    terminal(&sgr),
+                    terminal(&spec_sgr(to)),
+                    "from {from:?} to {to:?}: {:?}",
+                    String::from_utf8_lossy(&sgr),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn transition_to_same_state_is_empty() {
+        for spec in specs(40) {
+            let mut sgr = vec![];
+            ansi_transition(&mut sgr, &spec, &spec).unwrap();
+            assert!(sgr.is_empty(), "{:?}", String::from_utf8_lossy(&sgr));
+        }
+    }
+
+    #[test]
+    fn minimal_writer_matches_full_writer() {
+        let mut specs = specs(200);
+        // Layer some of the specifications on top of the previous state.
+        for spec in specs.iter_mut().skip(1).step_by(3) {
+            spec.set_reset(false);
+        }
+        let mut full = AnsiWriter::new(vec![]);
+        let mut minimal = AnsiWriter::new(vec![]);
+        minimal.set_minimal_transitions(true);
+        for spec in &specs {
+            full.set_color(spec).unwrap();
+            minimal.set_color(spec).unwrap();
+            assert_eq!(
+                terminal(minimal.get_ref()),
+                terminal(full.get_ref()),
+                "after {spec:?}",
+            );
+        }
+        assert!(minimal.get_ref().len() < full.get_ref().len());
+    }
+}
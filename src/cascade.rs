@@ -0,0 +1,129 @@
+//! Layering color specifications on top of each other.
+
+use crate::{Attribute, ColorSpec};
+
+impl ColorSpec {
+    /// Returns a new color specification with the settings of `overlay`
+    /// layered on top of this one.
+    ///
+    /// See `ColorSpec::apply` for the rules that are used.
+    pub fn merge(&self, overlay: &ColorSpec) -> ColorSpec {
+        let mut merged = *self;
+        merged.apply(overlay);
+        merged
+    }
+
+    /// Layer the settings of `overlay` on top of this color specification.
+    ///
+    /// The rules are as follows:
+    ///
+    /// * Each color that's set in `overlay` replaces the corresponding color
+    ///   of this specification. Unset colors are kept.
+    /// * Each attribute that's set in `overlay`, whether it's turned on or
+    ///   off, replaces the corresponding attribute of this specification.
+    ///   Unset attributes are kept. The underline style is taken along with
+    ///   the underline. Turning the underline off also clears its style (see
+    ///   `ColorSpec::set_attr`), so it doesn't come back when a later layer
+    ///   turns the underline on again.
+    /// * Intensity modifies the foreground color, so if `overlay` sets a
+    ///   foreground color, then its intensity is taken along with it.
+    ///   Otherwise, the result is intense if either specification is.
+    /// * Reset is kept as is, since it concerns the terminal state that the
+    ///   bottom layer is applied to, and not any of the layers above it.
+    pub fn apply(&mut self, overlay: &ColorSpec) -> &mut ColorSpec {
+        if let Some(&c) = overlay.fg() {
+            self.set_fg(Some(c));
+            self.set_intense(overlay.intense());
+        } else if overlay.intense() {
+            self.set_intense(true);
+        }
+        if let Some(&c) = overlay.bg() {
+            self.set_bg(Some(c));
+        }
+        if let Some(&c) = overlay.underline_color() {
+            self.set_underline_color(Some(c));
+        }
+        for attr in Attribute::ALL {
+            if let Some(state) = overlay.attr(attr) {
+                self.set_attr(attr, Some(state));
+            }
+        }
+        if overlay.underline() {
+            self.set_underline_style(overlay.underline_style());
+        }
+        self
+    }
+}
+
+/// A stack of color specifications that are layered on top of each other.
+///
+/// This is useful for nested styles, e.g., a base style for some text, a
+/// semantic style such as "error" for part of it and a local override such
+/// as "focused" for part of that. Each layer is merged on top of the layers
+/// below it with `ColorSpec::apply`, and `Cascade::resolve` returns the
+/// effective specification of the whole stack.
+///
+/// Pushing and popping are cheap, since the effective specification of each
+/// layer is computed once when it's pushed.
+#[derive(Clone, Debug, Default)]
+pub struct Cascade {
+    /// Each layer along with the effective specification of the stack up to
+    /// and including it.
+    layers: Vec<(ColorSpec, ColorSpec)>,
+}
+
+impl Cascade {
+    /// Create a new empty cascade.
+    pub fn new() -> Cascade {
+        Cascade::default()
+    }
+
+    /// Push a new layer on top of this cascade.
+    pub fn push(&mut self, spec: ColorSpec) -> &mut Cascade {
+        let resolved = self.resolve().merge(&spec);
+        self.layers.push((spec, resolved));
+        self
+    }
+
+    /// Remove the top layer of this cascade and return it, or `None` if
+    /// this cascade is empty.
+    pub fn pop(&mut self) -> Option<ColorSpec> {
+        self.layers.pop().map(|(spec, _)| spec)
+    }
+
+    /// Returns the number of layers in this cascade.
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Returns true if this cascade has no layers.
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Returns the effective color specification of all layers.
+    ///
+    /// An empty cascade resolves to `ColorSpec::new()`.
+    pub fn resolve(&self) -> ColorSpec {
+        match self.layers.last() {
+            None => ColorSpec::new(),
+            Some(&(_, resolved)) => resolved,
+        }
+    }
+}
+
+impl FromIterator<ColorSpec> for Cascade {
+    fn from_iter<I: IntoIterator<Item = ColorSpec>>(iter: I) -> Cascade {
+        let mut cascade = Cascade::new();
+        cascade.extend(iter);
+        cascade
+    }
+}
+
+impl Extend<ColorSpec> for Cascade {
+    fn extend<I: IntoIterator<Item = ColorSpec>>(&mut self, iter: I) {
+        for spec in iter {
+            self.push(spec);
+        }
+    }
+}
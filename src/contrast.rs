@@ -0,0 +1,146 @@
+//! Contrast computations as defined by the Web Content Accessibility
+//! Guidelines (WCAG) 2.x.
+//!
+//! See: https://www.w3.org/TR/WCAG21/#dfn-contrast-ratio
+
+use crate::space::{Oklab, to_linear};
+use crate::{Color, ColorSpec, Palette};
+
+/// Returns the relative luminance of an sRGB color.
+fn luminance((r, g, b): (u8, u8, u8)) -> f64 {
+    0.2126 * to_linear(r) + 0.7152 * to_linear(g) + 0.0722 * to_linear(b)
+}
+
+/// Returns the contrast ratio between two sRGB colors.
+fn ratio(c1: (u8, u8, u8), c2: (u8, u8, u8)) -> f64 {
+    let (l1, l2) = (luminance(c1), luminance(c2));
+    (l1.max(l2) + 0.05) / (l1.min(l2) + 0.05)
+}
+
+impl Color {
+    /// Returns the relative luminance of this color, in the range `[0, 1]`.
+    ///
+    /// Named and `Ansi256` colors are resolved with the default xterm
+    /// palette. Use `Palette::relative_luminance` to pick another palette.
+    pub fn relative_luminance(&self) -> f64 {
+        Palette::xterm().relative_luminance(self)
+    }
+
+    /// Returns the WCAG contrast ratio between this color and `other`, in
+    /// the range `[1, 21]`.
+    ///
+    /// WCAG level AA asks for a ratio of at least 4.5 for normal text, and
+    /// level AAA for at least 7.
+    ///
+    /// Named and `Ansi256` colors are resolved with the default xterm
+    /// palette. Use `Palette::contrast_ratio` to pick another palette.
+    pub fn contrast_ratio(&self, other: &Color) -> f64 {
+        Palette::xterm().contrast_ratio(self, other)
+    }
+}
+
+impl Palette {
+    /// Returns the relative luminance of the given color in this palette,
+    /// in the range `[0, 1]`.
+    pub fn relative_luminance(&self, color: &Color) -> f64 {
+        luminance(self.resolve(color))
+    }
+
+    /// Returns the WCAG contrast ratio between two colors in this palette,
+    /// in the range `[1, 21]`.
+    ///
+    /// `Color::Default` is resolved as the default foreground for `fg` and
+    /// as the default background for `bg`.
+    pub fn contrast_ratio(&self, fg: &Color, bg: &Color) -> f64 {
+        ratio(self.resolve_fg(Some(fg)), self.resolve_bg(Some(bg)))
+    }
+}
+
+impl ColorSpec {
+    /// Returns the WCAG contrast ratio between the foreground and background
+    /// colors of this specification.
+    ///
+    /// Missing colors are resolved as the default foreground and background
+    /// of the given palette. An intense foreground is measured as its bright
+    /// variant.
+    pub fn contrast_ratio(&self, palette: &Palette) -> f64 {
+        ratio(
+            palette.resolve_fg(self.shown_fg().as_ref()),
+            palette.resolve_bg(self.bg()),
+        )
+    }
+
+    /// Adjust the foreground color so that it has a contrast ratio of at
+    /// least `min_ratio` against the background color.
+    ///
+    /// This is a shorthand for `ensure_contrast_with` using the default
+    /// xterm palette.
+    pub fn ensure_contrast(&mut self, min_ratio: f64) -> &mut ColorSpec {
+        self.ensure_contrast_with(min_ratio, &Palette::xterm())
+    }
+
+    /// Adjust the foreground color so that it has a contrast ratio of at
+    /// least `min_ratio` against the background color, using `palette` to
+    /// resolve named and `Ansi256` colors.
+    ///
+    /// If the contrast is already sufficient, then nothing changes.
+    /// Otherwise, the foreground is replaced by an `Rgb` color with the same
+    /// hue, lightened or darkened (whichever can reach the higher contrast)
+    /// only as far as needed. If no such color reaches `min_ratio`, then the
+    /// foreground is set to black or white, whichever contrasts more.
+    ///
+    /// Missing colors are resolved as the default foreground and background
+    /// of the palette. An intense foreground is measured as its bright
+    /// variant. `min_ratio` is clamped to `[1, 21]`.
+    pub fn ensure_contrast_with(
+        &mut self,
+        min_ratio: f64,
+        palette: &Palette,
+    ) -> &mut ColorSpec {
+        const BLACK: (u8, u8, u8) = (0, 0, 0);
+        const WHITE: (u8, u8, u8) = (255, 255, 255);
+
+        let min_ratio = min_ratio.clamp(1.0, 21.0);
+        let fg = palette.resolve_fg(self.shown_fg().as_ref());
+        let bg = palette.resolve_bg(self.bg());
+        if ratio(fg, bg) >= min_ratio {
+            return self;
+        }
+        let (lighten, extreme) = if ratio(WHITE, bg) >= ratio(BLACK, bg) {
+            (true, WHITE)
+        } else {
+            (false, BLACK)
+        };
+        if ratio(extreme, bg) < min_ratio {
+            let (r, g, b) = extreme;
+            return self.set_fg(Some(Color::Rgb(r, g, b)));
+        }
+        // Contrast grows monotonically as the lightness moves towards the
+        // extreme, so find the smallest lightness change that's sufficient.
+        let lab = Oklab::from_rgb(fg);
+        let with_lightness = |l: f64| Oklab { l, ..lab }.to_rgb();
+        let (mut near, mut far) = (lab.l, if lighten { 1.0 } else { 0.0 });
+        for _ in 0..24 {
+            let mid = (near + far) / 2.0;
+            if ratio(with_lightness(mid), bg) >= min_ratio {
+                far = mid;
+            } else {
+                near = mid;
+            }
+        }
+        let mut rgb = with_lightness(far);
+        // Clipping to the sRGB gamut can cost a little bit of contrast.
+        if ratio(rgb, bg) < min_ratio {
+            rgb = extreme;
+        }
+        let (r, g, b) = rgb;
+        self.set_fg(Some(Color::Rgb(r, g, b)))
+    }
+
+    /// Returns the foreground color as it is shown, i.e., the bright variant
+    /// of a base color when this specification is intense.
+    fn shown_fg(&self) -> Option<Color> {
+        let fg = self.fg().copied();
+        if self.intense() { fg.map(Color::to_bright) } else { fg }
+    }
+}
@@ -0,0 +1,294 @@
+//! Color vision deficiency (CVD) simulation and colorblind-safe colors.
+//!
+//! Simulation uses the matrices from Machado, Oliveira and Fernandes, "A
+//! Physiologically-based Model for Simulation of Color Vision Deficiency"
+//! (2009), at full severity. They're applied to linear RGB values.
+
+use std::env;
+
+use crate::Color;
+use crate::space::{from_linear, to_linear};
+
+/// The OKLCH chroma below which a color is treated as a gray.
+const ACHROMATIC: f64 = 0.03;
+
+/// A kind of color vision deficiency.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum VisionDeficiency {
+    /// The absence of red sensitive cones.
+    Protanopia,
+    /// The absence of green sensitive cones. This is the most common kind.
+    Deuteranopia,
+    /// The absence of blue sensitive cones.
+    Tritanopia,
+}
+
+impl VisionDeficiency {
+    /// Returns the matrix that simulates this deficiency in linear RGB.
+    fn matrix(self) -> [[f64; 3]; 3] {
+        match self {
+            VisionDeficiency::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            VisionDeficiency::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            VisionDeficiency::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ],
+        }
+    }
+
+    /// Returns how the given sRGB color looks with this deficiency.
+    fn simulate_rgb(self, (r, g, b): (u8, u8, u8)) -> (u8, u8, u8) {
+        let m = self.matrix();
+        let v = [to_linear(r), to_linear(g), to_linear(b)];
+        let row = |i: usize| m[i][0] * v[0] + m[i][1] * v[1] + m[i][2] * v[2];
+        (from_linear(row(0)), from_linear(row(1)), from_linear(row(2)))
+    }
+
+    /// Returns the given sRGB color with the information that's lost to
+    /// this deficiency shifted into channels that remain visible.
+    fn daltonize_rgb(self, rgb: (u8, u8, u8)) -> (u8, u8, u8) {
+        let sim = self.simulate_rgb(rgb);
+        let err = [
+            f64::from(rgb.0) - f64::from(sim.0),
+            f64::from(rgb.1) - f64::from(sim.1),
+            f64::from(rgb.2) - f64::from(sim.2),
+        ];
+        let shift = match self {
+            VisionDeficiency::Protanopia | VisionDeficiency::Deuteranopia => {
+                [0.0, 0.7 * err[0] + err[1], 0.7 * err[0] + err[2]]
+            }
+            VisionDeficiency::Tritanopia => {
+                [err[0] + 0.7 * err[2], err[1] + 0.7 * err[2], 0.0]
+            }
+        };
+        let channel =
+            |c: u8, d: f64| (f64::from(c) + d).round().clamp(0.0, 255.0) as u8;
+        (
+            channel(rgb.0, shift[0]),
+            channel(rgb.1, shift[1]),
+            channel(rgb.2, shift[2]),
+        )
+    }
+}
+
+impl Color {
+    /// Returns an `Rgb` color showing how this color looks to someone with
+    /// the given color vision deficiency.
+    ///
+    /// Named and `Ansi256` colors are resolved with the default xterm
+    /// palette. `Color::Default` is returned as is, since its value isn't
+    /// known.
+    pub fn simulate(&self, deficiency: VisionDeficiency) -> Color {
+        if *self == Color::Default {
+            return Color::Default;
+        }
+        let (r, g, b) = deficiency.simulate_rgb(self.to_rgb());
+        Color::Rgb(r, g, b)
+    }
+
+    /// Returns an `Rgb` color that's easier to tell apart from others for
+    /// someone with the given color vision deficiency.
+    ///
+    /// This "daltonizes" the color: the difference between this color and
+    /// its simulation is shifted into channels that remain visible. Colors
+    /// are resolved as in `Color::simulate`.
+    pub fn daltonize(&self, deficiency: VisionDeficiency) -> Color {
+        if *self == Color::Default {
+            return Color::Default;
+        }
+        let (r, g, b) = deficiency.daltonize_rgb(self.to_rgb());
+        Color::Rgb(r, g, b)
+    }
+}
+
+/// A transformation applied to every color a writer emits.
+///
+/// This can be enabled on the writers in this crate, e.g., with
+/// `AnsiWriter::set_color_transform`, to check or adapt an application's
+/// entire output without changing the colors it picks. The standard stream
+/// writers also pick it up from the environment; see
+/// `ColorTransform::from_env`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ColorTransform {
+    /// Show colors as they look with the given deficiency. See
+    /// `Color::simulate`.
+    Simulate(VisionDeficiency),
+    /// Adapt colors to be easier to distinguish with the given deficiency.
+    /// See `Color::daltonize`.
+    Daltonize(VisionDeficiency),
+    /// Replace every color with the closest color of the colorblind-safe
+    /// Okabe-Ito palette. See `OkabeIto::nearest`. Grays are kept as is,
+    /// since they're told apart by lightness alone.
+    OkabeIto,
+}
+
+impl ColorTransform {
+    /// The name of the environment variable that `ColorTransform::from_env`
+    /// reads.
+    pub const ENV_VAR: &'static str = "TERMCOLOR_CVD";
+
+    /// Returns the transformation selected by the `TERMCOLOR_CVD`
+    /// environment variable, if any.
+    ///
+    /// `StandardStream`, `BufferedStandardStream` and `BufferWriter` use
+    /// this as their initial color transform, so that an application's
+    /// entire output can be checked or adapted without changing its code.
+    /// The recognized values, which are case insensitive, are:
+    ///
+    /// * `protanopia`, `deuteranopia` or `tritanopia`, to simulate the given
+    ///   deficiency.
+    /// * `daltonize-protanopia`, `daltonize-deuteranopia` or
+    ///   `daltonize-tritanopia`, to adapt colors to the given deficiency.
+    /// * `okabe-ito`, to map colors to the Okabe-Ito palette.
+    ///
+    /// `None` is returned if the variable is unset, empty or set to any
+    /// other value.
+    pub fn from_env() -> Option<ColorTransform> {
+        let value = env::var(ColorTransform::ENV_VAR).ok()?;
+        let value = value.trim().to_ascii_lowercase();
+        if value == "okabe-ito" {
+            return Some(ColorTransform::OkabeIto);
+        }
+        let (daltonize, name) = match value.strip_prefix("daltonize-") {
+            Some(name) => (true, name),
+            None => (false, &*value),
+        };
+        let deficiency = match name {
+            "protanopia" => VisionDeficiency::Protanopia,
+            "deuteranopia" => VisionDeficiency::Deuteranopia,
+            "tritanopia" => VisionDeficiency::Tritanopia,
+            _ => return None,
+        };
+        Some(if daltonize {
+            ColorTransform::Daltonize(deficiency)
+        } else {
+            ColorTransform::Simulate(deficiency)
+        })
+    }
+
+    /// Apply this transformation to a color.
+    pub fn apply(&self, color: &Color) -> Color {
+        match *self {
+            ColorTransform::Simulate(d) => color.simulate(d),
+            ColorTransform::Daltonize(d) => color.daltonize(d),
+            ColorTransform::OkabeIto => {
+                if *color == Color::Default || color.to_oklch().1 < ACHROMATIC
+                {
+                    *color
+                } else {
+                    OkabeIto::nearest(color).into()
+                }
+            }
+        }
+    }
+}
+
+/// The colors of the Okabe-Ito palette, which remain distinguishable for
+/// people with the common kinds of color vision deficiency.
+///
+/// Each variant converts into an `Rgb` color, so it can be used anywhere a
+/// `Color` is expected, e.g., `spec.set_fg(Some(OkabeIto::ERROR.into()))`.
+///
+/// The associated constants give suggested colors for common semantic roles.
+/// Notably, errors and successes use vermillion and bluish green instead of
+/// the usual red and green, which are easily confused.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum OkabeIto {
+    /// `#000000`
+    Black,
+    /// `#e69f00`
+    Orange,
+    /// `#56b4e9`
+    SkyBlue,
+    /// `#009e73`
+    BluishGreen,
+    /// `#f0e442`
+    Yellow,
+    /// `#0072b2`
+    Blue,
+    /// `#d55e00`
+    Vermillion,
+    /// `#cc79a7`
+    ReddishPurple,
+}
+
+impl OkabeIto {
+    /// The color for errors.
+    pub const ERROR: OkabeIto = OkabeIto::Vermillion;
+    /// The color for warnings.
+    pub const WARNING: OkabeIto = OkabeIto::Orange;
+    /// The color for successes.
+    pub const SUCCESS: OkabeIto = OkabeIto::BluishGreen;
+    /// The color for informational messages.
+    pub const INFO: OkabeIto = OkabeIto::SkyBlue;
+    /// The color for links and other highlights.
+    pub const LINK: OkabeIto = OkabeIto::Blue;
+    /// The color for notes and hints.
+    pub const NOTE: OkabeIto = OkabeIto::ReddishPurple;
+
+    /// Every color in the palette.
+    pub const ALL: [OkabeIto; 8] = [
+        OkabeIto::Black,
+        OkabeIto::Orange,
+        OkabeIto::SkyBlue,
+        OkabeIto::BluishGreen,
+        OkabeIto::Yellow,
+        OkabeIto::Blue,
+        OkabeIto::Vermillion,
+        OkabeIto::ReddishPurple,
+    ];
+
+    /// Returns the RGB value of this color.
+    pub fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            OkabeIto::Black => (0, 0, 0),
+            OkabeIto::Orange => (230, 159, 0),
+            OkabeIto::SkyBlue => (86, 180, 233),
+            OkabeIto::BluishGreen => (0, 158, 115),
+            OkabeIto::Yellow => (240, 228, 66),
+            OkabeIto::Blue => (0, 114, 178),
+            OkabeIto::Vermillion => (213, 94, 0),
+            OkabeIto::ReddishPurple => (204, 121, 167),
+        }
+    }
+
+    /// Returns the palette color closest to the given color.
+    ///
+    /// Colors are compared by hue first, since that's what usually carries
+    /// their meaning, and by lightness second.
+    pub fn nearest(color: &Color) -> OkabeIto {
+        let (l, c, h) = color.to_oklch();
+        let distance = |candidate: OkabeIto| {
+            let (cl, cc, ch) = Color::from(candidate).to_oklch();
+            let dl = l - cl;
+            // Hue is meaningless for a gray, so compare chroma instead.
+            if c < ACHROMATIC || cc < ACHROMATIC {
+                let dc = c - cc;
+                return dl * dl + 4.0 * dc * dc;
+            }
+            let dh = (h - ch).abs();
+            let dh = dh.min(360.0 - dh) / 180.0;
+            dh * dh + 0.25 * dl * dl
+        };
+        OkabeIto::ALL
+            .into_iter()
+            .min_by(|&a, &b| distance(a).total_cmp(&distance(b)))
+            .expect("a non-empty palette")
+    }
+}
+
+impl From<OkabeIto> for Color {
+    fn from(color: OkabeIto) -> Color {
+        let (r, g, b) = color.rgb();
+        Color::Rgb(r, g, b)
+    }
+}
@@ -0,0 +1,152 @@
+//! Gradients that blend colors over the characters of written text.
+
+use std::io;
+
+use crate::{Color, ColorSpec, WriteColor};
+
+/// A color gradient that can be used to write text.
+///
+/// A gradient is defined by two or more color stops, which are spread
+/// evenly over the text that's written. The colors in between are found with
+/// `Color::lerp`.
+///
+/// When writing, each color is reduced to the writer's
+/// `WriteColor::color_level`, so a gradient degrades to the closest colors
+/// that the writer can display.
+#[derive(Clone, Debug)]
+pub struct Gradient {
+    stops: Vec<Color>,
+    spec: ColorSpec,
+    background: bool,
+}
+
+impl Gradient {
+    /// Create a new gradient that runs through the given color stops in
+    /// order.
+    ///
+    /// A gradient with a single stop is a solid color. A gradient with no
+    /// stops writes text without changing its color.
+    pub fn new(stops: Vec<Color>) -> Gradient {
+        Gradient { stops, spec: ColorSpec::new(), background: false }
+    }
+
+    /// Set the color specification used for the styles of the text, such as
+    /// bold or italic.
+    ///
+    /// The gradient overrides the foreground color of this specification
+    /// (or its background color, when applied to the background), but its
+    /// other settings are kept.
+    pub fn set_spec(&mut self, spec: ColorSpec) -> &mut Gradient {
+        self.spec = spec;
+        self
+    }
+
+    /// Set whether the gradient is applied to the background color instead
+    /// of the foreground color.
+    pub fn set_background(&mut self, yes: bool) -> &mut Gradient {
+        self.background = yes;
+        self
+    }
+
+    /// Returns the color a fraction `t` of the way through this gradient.
+    ///
+    /// `t` is clamped to `[0, 1]`. If this gradient has no stops, then
+    /// `None` is returned.
+    pub fn at(&self, t: f64) -> Option<Color> {
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+        match self.stops.len() {
+            0 => None,
+            1 => Some(self.stops[0]),
+            n => {
+                let pos = t * (n - 1) as f64;
+                let i = (pos.floor() as usize).min(n - 2);
+                Some(self.stops[i].lerp(&self.stops[i + 1], pos - i as f64))
+            }
+        }
+    }
+
+    /// Write the given text to `wtr`, coloring each character with the
+    /// next color of this gradient.
+    ///
+    /// Combining marks, variation selectors and zero width joiners are kept
+    /// with the character that precedes them, so that most grapheme
+    /// clusters get a single color.
+    ///
+    /// A gradient without any stops leaves the colors of its base spec
+    /// untouched.
+    ///
+    /// The writer's color settings are reset once all of the text has been
+    /// written.
+    pub fn write<W: WriteColor + ?Sized>(
+        &self,
+        wtr: &mut W,
+        text: &str,
+    ) -> io::Result<()> {
+        let clusters = clusters(text);
+        let level = wtr.color_level();
+        let mut spec = self.spec;
+        let mut last = None;
+        for (i, cluster) in clusters.iter().enumerate() {
+            let t = if clusters.len() <= 1 {
+                0.0
+            } else {
+                i as f64 / (clusters.len() - 1) as f64
+            };
+            let color = self.at(t).map(|c| c.to_level(level));
+            if i == 0 || color != last {
+                if let Some(color) = color {
+                    if self.background {
+                        spec.set_bg(color);
+                    } else {
+                        spec.set_fg(color);
+                    }
+                }
+                wtr.set_color(&spec)?;
+                // Only the first color needs a reset, if one was asked for.
+                spec.set_reset(false);
+                last = color;
+            }
+            wtr.write_all(cluster.as_bytes())?;
+        }
+        wtr.reset()
+    }
+}
+
+/// Splits text into approximate grapheme clusters.
+///
+/// Every character starts a new cluster, except for combining marks,
+/// variation selectors, emoji modifiers, zero width joiners and the
+/// character that follows a zero width joiner.
+fn clusters(text: &str) -> Vec<&str> {
+    let mut clusters = vec![];
+    let mut start = 0;
+    let mut joined = false;
+    for (i, c) in text.char_indices() {
+        let extends = joined || is_extender(c);
+        joined = c == '\u{200D}';
+        if i > start && !extends {
+            clusters.push(&text[start..i]);
+            start = i;
+        }
+    }
+    if start < text.len() {
+        clusters.push(&text[start..]);
+    }
+    clusters
+}
+
+/// Returns true if the given character extends the cluster before it.
+fn is_extender(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0300}'..='\u{036F}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{200D}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE00}'..='\u{FE0F}'
+            | '\u{FE20}'..='\u{FE2F}'
+            | '\u{1F3FB}'..='\u{1F3FF}'
+            | '\u{E0100}'..='\u{E01EF}'
+    )
+}
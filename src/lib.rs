@@ -1,18 +1,44 @@
 //! Termcolor crate for cross-platform colored terminal output
 
 pub mod ansi;
+mod cascade;
+mod contrast;
+mod cvd;
+mod gradient;
+mod ls_colors;
+mod names;
+mod palette;
+mod quantize;
+#[cfg(feature = "serde")]
+mod serde_impl;
+mod sgr;
+mod sgr_colors;
+mod space;
+mod theme;
 mod traits;
 mod types;
 mod writers;
 
 // Re-export core traits and types
-pub use ansi::{AnsiColor, ansi_color, ansi_color_only, ansi_spec};
+pub use ansi::{
+    AnsiColor, ansi_color, ansi_color_only, ansi_spec, ansi_transition,
+};
+pub use cascade::Cascade;
+pub use cvd::{ColorTransform, OkabeIto, VisionDeficiency};
+pub use gradient::Gradient;
+pub use ls_colors::{FileIndicator, LsColors};
+pub use palette::Palette;
+#[cfg(feature = "serde")]
+pub use serde_impl::serde_map;
+pub use sgr_colors::{SgrColors, SgrPreset};
+pub use theme::{Theme, ThemeError, Themed};
 pub use traits::WriteColor;
 pub use types::{
-    Color, ColorChoice, ColorChoiceParseError, ColorSpec, ColorSpecParseError,
-    HyperlinkSpec, ParseColorError,
+    Attribute, Color, ColorChoice, ColorChoiceParseError, ColorLevel,
+    ColorSpec, ColorSpecParseError, HyperlinkSpec, ParseColorError,
+    UnderlineStyle,
 };
 pub use writers::{
-    Ansi, Buffer, BufferWriter, BufferedStandardStream, NoColor,
+    Ansi, AnsiWriter, Buffer, BufferWriter, BufferedStandardStream, NoColor,
     StandardStream, StandardStreamLock, StringWriter, TermString,
 };
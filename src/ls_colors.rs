@@ -0,0 +1,498 @@
+//! Styling file paths the way `ls` does, based on `LS_COLORS`.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::sgr::style_from_params;
+use crate::{ColorSpec, WriteColor};
+
+/// The file type styles of the `dircolors` database.
+const DIRCOLORS_TYPES: &str = "rs=0:di=01;34:ln=01;36:mh=00:pi=40;33:\
+    so=01;35:do=01;35:bd=40;33;01:cd=40;33;01:or=40;31;01:mi=00:su=37;41:\
+    sg=30;43:ca=00:tw=30;42:ow=34;42:st=37;44:ex=01;32";
+
+/// The file name patterns of the `dircolors` database, grouped by style.
+const DIRCOLORS_PATTERNS: &[(&str, &[&str])] = &[
+    // Archives and compressed files.
+    (
+        "01;31",
+        &[
+            "*.7z", "*.ace", "*.alz", "*.apk", "*.arc", "*.arj", "*.bz",
+            "*.bz2", "*.cab", "*.cpio", "*.crate", "*.deb", "*.drpm", "*.dwm",
+            "*.dz", "*.ear", "*.egg", "*.esd", "*.gz", "*.jar", "*.lha",
+            "*.lrz", "*.lz", "*.lz4", "*.lzh", "*.lzma", "*.lzo", "*.pyz",
+            "*.rar", "*.rpm", "*.rz", "*.sar", "*.swm", "*.t7z", "*.tar",
+            "*.taz", "*.tbz", "*.tbz2", "*.tgz", "*.tlz", "*.txz", "*.tz",
+            "*.tzo", "*.tzst", "*.udeb", "*.war", "*.whl", "*.wim", "*.xz",
+            "*.z", "*.zip", "*.zoo", "*.zst",
+        ],
+    ),
+    // Images and videos.
+    (
+        "01;35",
+        &[
+            "*.avif", "*.jpg", "*.jpeg", "*.jxl", "*.mjpg", "*.mjpeg",
+            "*.gif", "*.bmp", "*.pbm", "*.pgm", "*.ppm", "*.tga", "*.xbm",
+            "*.xpm", "*.tif", "*.tiff", "*.png", "*.svg", "*.svgz", "*.mng",
+            "*.pcx", "*.mov", "*.mpg", "*.mpeg", "*.m2v", "*.mkv", "*.webm",
+            "*.webp", "*.ogm", "*.mp4", "*.m4v", "*.mp4v", "*.vob", "*.qt",
+            "*.nuv", "*.wmv", "*.asf", "*.rm", "*.rmvb", "*.flc", "*.avi",
+            "*.fli", "*.flv", "*.gl", "*.dl", "*.xcf", "*.xwd", "*.yuv",
+            "*.cgm", "*.emf", "*.ogv", "*.ogx",
+        ],
+    ),
+    // Audio.
+    (
+        "00;36",
+        &[
+            "*.aac", "*.au", "*.flac", "*.m4a", "*.mid", "*.midi", "*.mka",
+            "*.mp3", "*.mpc", "*.ogg", "*.ra", "*.wav", "*.oga", "*.opus",
+            "*.spx", "*.xspf",
+        ],
+    ),
+    // Backups and temporary files.
+    (
+        "00;90",
+        &[
+            "*~",
+            "*#",
+            "*.bak",
+            "*.crdownload",
+            "*.dpkg-dist",
+            "*.dpkg-new",
+            "*.dpkg-old",
+            "*.dpkg-tmp",
+            "*.old",
+            "*.orig",
+            "*.part",
+            "*.rej",
+            "*.rpmnew",
+            "*.rpmorig",
+            "*.rpmsave",
+            "*.swp",
+            "*.tmp",
+            "*.ucf-dist",
+            "*.ucf-new",
+            "*.ucf-old",
+        ],
+    ),
+];
+
+/// A kind of file that `LS_COLORS` assigns a style to, identified by a two
+/// letter code.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum FileIndicator {
+    /// `no`: the style of anything that has no other style.
+    Normal,
+    /// `fi`: a regular file.
+    File,
+    /// `di`: a directory.
+    Directory,
+    /// `ln`: a symbolic link.
+    Symlink,
+    /// `mh`: a regular file with more than one hard link.
+    MultiHardlink,
+    /// `pi`: a named pipe.
+    Fifo,
+    /// `so`: a socket.
+    Socket,
+    /// `do`: a door.
+    Door,
+    /// `bd`: a block device.
+    BlockDevice,
+    /// `cd`: a character device.
+    CharDevice,
+    /// `or`: a symbolic link whose target doesn't exist.
+    Orphan,
+    /// `mi`: a file that doesn't exist.
+    Missing,
+    /// `su`: a file that's set-user-ID.
+    Setuid,
+    /// `sg`: a file that's set-group-ID.
+    Setgid,
+    /// `ca`: a file with capabilities.
+    Capability,
+    /// `tw`: a directory that's sticky and writable by others.
+    StickyOtherWritable,
+    /// `ow`: a directory that's writable by others.
+    OtherWritable,
+    /// `st`: a directory that's sticky.
+    Sticky,
+    /// `ex`: an executable file.
+    Executable,
+}
+
+impl FileIndicator {
+    /// Returns the code of this indicator in `LS_COLORS`, e.g., `di`.
+    pub fn code(self) -> &'static str {
+        use self::FileIndicator::*;
+        match self {
+            Normal => "no",
+            File => "fi",
+            Directory => "di",
+            Symlink => "ln",
+            MultiHardlink => "mh",
+            Fifo => "pi",
+            Socket => "so",
+            Door => "do",
+            BlockDevice => "bd",
+            CharDevice => "cd",
+            Orphan => "or",
+            Missing => "mi",
+            Setuid => "su",
+            Setgid => "sg",
+            Capability => "ca",
+            StickyOtherWritable => "tw",
+            OtherWritable => "ow",
+            Sticky => "st",
+            Executable => "ex",
+        }
+    }
+
+    /// Returns the indicator with the given code in `LS_COLORS`, if any.
+    pub fn from_code(code: &str) -> Option<FileIndicator> {
+        use self::FileIndicator::*;
+        Some(match code {
+            "no" => Normal,
+            "fi" => File,
+            "di" => Directory,
+            "ln" => Symlink,
+            "mh" => MultiHardlink,
+            "pi" => Fifo,
+            "so" => Socket,
+            "do" => Door,
+            "bd" => BlockDevice,
+            "cd" => CharDevice,
+            "or" => Orphan,
+            "mi" => Missing,
+            "su" => Setuid,
+            "sg" => Setgid,
+            "ca" => Capability,
+            "tw" => StickyOtherWritable,
+            "ow" => OtherWritable,
+            "st" => Sticky,
+            "ex" => Executable,
+            _ => return None,
+        })
+    }
+}
+
+/// A table of styles for file paths, as configured by `LS_COLORS`.
+///
+/// The table assigns styles to kinds of files, such as directories or
+/// executables, and to file name patterns, such as `*.tar`. Patterns may
+/// use `*` to match any sequence of characters and `?` to match a single
+/// character. Like in `ls`, patterns only apply to regular files that
+/// aren't set-user-ID, set-group-ID, executable or multiply hard linked
+/// (unless those have no style), and later patterns take precedence over
+/// earlier ones. A pattern that matches a file name with the same case is
+/// preferred, but patterns match regardless of ASCII case otherwise.
+///
+/// The `Default` implementation returns `LsColors::dircolors`.
+#[derive(Clone, Debug)]
+pub struct LsColors {
+    indicators: HashMap<FileIndicator, ColorSpec>,
+    link_target: bool,
+    patterns: Vec<(Vec<char>, ColorSpec)>,
+}
+
+impl Default for LsColors {
+    fn default() -> LsColors {
+        LsColors::dircolors()
+    }
+}
+
+impl LsColors {
+    /// Create a new table without any styles.
+    pub fn new() -> LsColors {
+        LsColors {
+            indicators: HashMap::new(),
+            link_target: false,
+            patterns: vec![],
+        }
+    }
+
+    /// Returns the table of the default `dircolors` database, which is
+    /// what `LS_COLORS` is usually set to.
+    pub fn dircolors() -> LsColors {
+        let mut table = LsColors::parse(DIRCOLORS_TYPES);
+        for &(sgr, globs) in DIRCOLORS_PATTERNS {
+            let spec = style_from_params(sgr).expect("valid dircolors style");
+            for glob in globs {
+                table.add_pattern(glob, spec);
+            }
+        }
+        table
+    }
+
+    /// Returns the table configured by the `LS_COLORS` environment
+    /// variable, or `LsColors::dircolors` if it's unset or empty.
+    pub fn from_env() -> LsColors {
+        match env::var("LS_COLORS") {
+            Ok(v) if !v.is_empty() => LsColors::parse(&v),
+            _ => LsColors::dircolors(),
+        }
+    }
+
+    /// Parse a table in the `LS_COLORS` format, e.g.,
+    /// `di=01;34:ln=target:*.tar=01;31`.
+    ///
+    /// Each entry maps a `FileIndicator` code or a file name pattern, which
+    /// must start with `*`, to SGR parameters. `ln=target` styles symbolic
+    /// links like the files they point to. A style that sets nothing, such
+    /// as `00`, leaves a kind of file unstyled, but still overrides the
+    /// style of its kind for files that match a pattern.
+    ///
+    /// Like `ls`, this ignores entries that it doesn't understand, such as
+    /// the `lc`, `rc` and `ec` escape codes.
+    pub fn parse(ls_colors: &str) -> LsColors {
+        let mut table = LsColors::new();
+        for entry in ls_colors.split(':') {
+            let Some((key, value)) = entry.split_once('=') else {
+                continue;
+            };
+            if key.starts_with('*') {
+                if let Some(spec) = style_from_params(value) {
+                    table.add_pattern(key, spec);
+                }
+            } else if let Some(indicator) = FileIndicator::from_code(key) {
+                if indicator == FileIndicator::Symlink && value == "target" {
+                    table.set_link_target(true);
+                } else if let Some(spec) = style_from_params(value) {
+                    table.set_indicator(indicator, Some(spec));
+                }
+            }
+        }
+        table
+    }
+
+    /// Returns the style of the given kind of file, if it has one.
+    pub fn indicator(&self, indicator: FileIndicator) -> Option<&ColorSpec> {
+        self.indicators.get(&indicator)
+    }
+
+    /// Set the style of the given kind of file.
+    ///
+    /// A style that sets nothing is the same as `None`.
+    pub fn set_indicator(
+        &mut self,
+        indicator: FileIndicator,
+        spec: Option<ColorSpec>,
+    ) -> &mut LsColors {
+        match spec {
+            Some(spec) if !spec.is_none() => {
+                self.indicators.insert(indicator, spec);
+            }
+            _ => {
+                self.indicators.remove(&indicator);
+            }
+        }
+        self
+    }
+
+    /// Returns true if symbolic links are styled like the files they point
+    /// to, i.e., if the table contains `ln=target`.
+    pub fn link_target(&self) -> bool {
+        self.link_target
+    }
+
+    /// Set whether symbolic links are styled like the files they point to.
+    ///
+    /// Links whose target doesn't exist are styled as
+    /// `FileIndicator::Orphan` either way, if that has a style.
+    pub fn set_link_target(&mut self, yes: bool) -> &mut LsColors {
+        self.link_target = yes;
+        self
+    }
+
+    /// Add a style for the file names that match the given pattern, e.g.,
+    /// `*.tar`.
+    ///
+    /// The pattern takes precedence over all patterns that were added
+    /// before it.
+    pub fn add_pattern(
+        &mut self,
+        glob: &str,
+        spec: ColorSpec,
+    ) -> &mut LsColors {
+        self.patterns.push((glob.chars().collect(), spec));
+        self
+    }
+
+    /// Returns the style of the last pattern that matches the given file
+    /// name, if any.
+    pub fn style_for_name(&self, name: &str) -> Option<&ColorSpec> {
+        let name: Vec<char> = name.chars().collect();
+        let find = |ignore_case: bool| {
+            self.patterns
+                .iter()
+                .rev()
+                .find(|(glob, _)| glob_match(glob, &name, ignore_case))
+                .map(|(_, spec)| spec)
+        };
+        find(false).or_else(|| find(true))
+    }
+
+    /// Returns the style of the file at the given path, if it has one.
+    ///
+    /// This inspects the file with `std::fs::symlink_metadata`, and follows
+    /// symbolic links as needed to detect orphans and for `ln=target`.
+    /// Files that can't be inspected are styled as missing files.
+    pub fn style_for_path(&self, path: &Path) -> Option<&ColorSpec> {
+        let mut meta = match fs::symlink_metadata(path) {
+            Ok(meta) => meta,
+            Err(_) => return self.style(FileIndicator::Missing),
+        };
+        // With `ln=target`, a link is styled by the name and kind of the
+        // file that it points to.
+        let mut name = path.file_name().unwrap_or(path.as_os_str()).to_owned();
+        if meta.file_type().is_symlink() {
+            match fs::metadata(path) {
+                Err(_) => {
+                    return self
+                        .indicator(FileIndicator::Orphan)
+                        .or_else(|| self.style(FileIndicator::Symlink));
+                }
+                Ok(target) if self.link_target => {
+                    meta = target;
+                    if let Ok(link) = fs::read_link(path)
+                        && let Some(target_name) = link.file_name()
+                    {
+                        name = target_name.to_owned();
+                    }
+                }
+                Ok(_) => return self.style(FileIndicator::Symlink),
+            }
+        }
+        let indicator = self.classify(&meta);
+        if indicator == FileIndicator::File
+            && let Some(spec) = self.style_for_name(&name.to_string_lossy())
+        {
+            return Some(spec);
+        }
+        self.style(indicator)
+    }
+
+    /// Write the given path to `wtr`, styled according to this table.
+    ///
+    /// The colors are reset after the path, if it was styled.
+    pub fn paint_path<W: WriteColor + ?Sized>(
+        &self,
+        wtr: &mut W,
+        path: &Path,
+    ) -> io::Result<()> {
+        match self.style_for_path(path).filter(|spec| !spec.is_none()) {
+            None => write!(wtr, "{}", path.display()),
+            Some(spec) => {
+                wtr.set_color(spec)?;
+                write!(wtr, "{}", path.display())?;
+                wtr.reset()
+            }
+        }
+    }
+
+    /// Returns the style of the given kind of file, falling back to the
+    /// style of `FileIndicator::Normal`.
+    fn style(&self, indicator: FileIndicator) -> Option<&ColorSpec> {
+        self.indicator(indicator)
+            .or_else(|| self.indicator(FileIndicator::Normal))
+    }
+
+    /// Returns the kind of a file that isn't a symbolic link, preferring
+    /// more specific kinds only if they have a style.
+    ///
+    /// `FileIndicator::Orphan` is never returned, since it's only for
+    /// symbolic links whose target doesn't exist.
+    #[cfg(unix)]
+    fn classify(&self, meta: &fs::Metadata) -> FileIndicator {
+        use self::FileIndicator::*;
+        use std::os::unix::fs::{FileTypeExt, MetadataExt};
+
+        // The file type bits of the mode, and the type of Solaris and
+        // illumos doors, which no other system uses.
+        const S_IFMT: u32 = 0o170000;
+        const S_IFDOOR: u32 = 0o150000;
+
+        let styled = |candidates: &[(bool, FileIndicator)]| {
+            candidates
+                .iter()
+                .find(|&&(yes, ind)| yes && self.indicator(ind).is_some())
+                .map(|&(_, ind)| ind)
+        };
+        let (file_type, mode) = (meta.file_type(), meta.mode());
+        let (sticky, other_writable) = (mode & 0o1000 != 0, mode & 0o002 != 0);
+        if file_type.is_dir() {
+            styled(&[
+                (sticky && other_writable, StickyOtherWritable),
+                (other_writable, OtherWritable),
+                (sticky, Sticky),
+            ])
+            .unwrap_or(Directory)
+        } else if file_type.is_file() {
+            styled(&[
+                (mode & 0o4000 != 0, Setuid),
+                (mode & 0o2000 != 0, Setgid),
+                (mode & 0o111 != 0, Executable),
+                (meta.nlink() > 1, MultiHardlink),
+            ])
+            .unwrap_or(File)
+        } else if file_type.is_fifo() {
+            Fifo
+        } else if file_type.is_socket() {
+            Socket
+        } else if file_type.is_block_device() {
+            BlockDevice
+        } else if file_type.is_char_device() {
+            CharDevice
+        } else if mode & S_IFMT == S_IFDOOR {
+            Door
+        } else {
+            // Other kinds of files, such as whiteouts, have no style of
+            // their own.
+            Normal
+        }
+    }
+
+    /// Returns the kind of a file that isn't a symbolic link.
+    #[cfg(not(unix))]
+    fn classify(&self, meta: &fs::Metadata) -> FileIndicator {
+        if meta.is_dir() {
+            FileIndicator::Directory
+        } else if meta.is_file() {
+            FileIndicator::File
+        } else {
+            FileIndicator::Normal
+        }
+    }
+}
+
+/// Returns true if `name` matches `glob`, where `*` matches any sequence of
+/// characters and `?` matches any single character.
+fn glob_match(glob: &[char], name: &[char], ignore_case: bool) -> bool {
+    let eq = |g: char, c: char| {
+        g == '?' || g == c || (ignore_case && g.eq_ignore_ascii_case(&c))
+    };
+    let (mut g, mut n) = (0, 0);
+    // The position after the last `*` and the position in `name` that it
+    // currently matches up to, for backtracking.
+    let mut star = None;
+    while n < name.len() {
+        if g < glob.len() && glob[g] == '*' {
+            star = Some((g + 1, n));
+            g += 1;
+        } else if g < glob.len() && eq(glob[g], name[n]) {
+            g += 1;
+            n += 1;
+        } else if let Some((after, matched)) = star {
+            star = Some((after, matched + 1));
+            g = after;
+            n = matched + 1;
+        } else {
+            return false;
+        }
+    }
+    glob[g..].iter().all(|&c| c == '*')
+}
@@ -0,0 +1,108 @@
+//! Reference palettes for resolving named and `Ansi256` colors to RGB.
+
+use crate::Color;
+use crate::space::{XTERM_BASE16, ansi256_to_rgb};
+
+/// A reference palette that maps colors to the RGB values a terminal
+/// displays them as.
+///
+/// Named colors and `Ansi256` colors don't have a fixed RGB value, since
+/// terminals let users pick their own palette. Computations that need actual
+/// RGB values, such as contrast checks, resolve those colors through a
+/// `Palette`. The 6x6x6 color cube and grayscale ramp of the 256 color
+/// palette are the same everywhere, so only the first 16 colors and the
+/// default foreground and background colors are configurable.
+///
+/// The `Default` implementation returns `Palette::xterm`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Palette {
+    base16: [(u8, u8, u8); 16],
+    foreground: (u8, u8, u8),
+    background: (u8, u8, u8),
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Palette::xterm()
+    }
+}
+
+impl Palette {
+    /// Returns the default xterm palette, with a light gray default
+    /// foreground on a black default background.
+    pub fn xterm() -> Palette {
+        Palette {
+            base16: XTERM_BASE16,
+            foreground: XTERM_BASE16[7],
+            background: XTERM_BASE16[0],
+        }
+    }
+
+    /// Create a palette from the RGB values of the 16 named colors, indexed
+    /// by their ANSI number (i.e., black, red, green, yellow, blue, magenta,
+    /// cyan, white and then their bright variants).
+    ///
+    /// The default foreground and background are initialized to white and
+    /// black from the given colors.
+    pub fn new(base16: [(u8, u8, u8); 16]) -> Palette {
+        Palette { base16, foreground: base16[7], background: base16[0] }
+    }
+
+    /// Set the RGB value of the terminal's default foreground color.
+    pub fn set_foreground(&mut self, rgb: (u8, u8, u8)) -> &mut Palette {
+        self.foreground = rgb;
+        self
+    }
+
+    /// Set the RGB value of the terminal's default background color.
+    pub fn set_background(&mut self, rgb: (u8, u8, u8)) -> &mut Palette {
+        self.background = rgb;
+        self
+    }
+
+    /// Returns the RGB value of the terminal's default foreground color.
+    pub fn foreground(&self) -> (u8, u8, u8) {
+        self.foreground
+    }
+
+    /// Returns the RGB value of the terminal's default background color.
+    pub fn background(&self) -> (u8, u8, u8) {
+        self.background
+    }
+
+    /// Returns the RGB value of a foreground color.
+    ///
+    /// `None` and `Color::Default` resolve to the default foreground color.
+    pub fn resolve_fg(&self, color: Option<&Color>) -> (u8, u8, u8) {
+        match color {
+            None | Some(Color::Default) => self.foreground,
+            Some(c) => self.resolve(c),
+        }
+    }
+
+    /// Returns the RGB value of a background color.
+    ///
+    /// `None` and `Color::Default` resolve to the default background color.
+    pub fn resolve_bg(&self, color: Option<&Color>) -> (u8, u8, u8) {
+        match color {
+            None | Some(Color::Default) => self.background,
+            Some(c) => self.resolve(c),
+        }
+    }
+
+    /// Returns the RGB value of the given color in this palette.
+    ///
+    /// `Color::Default` resolves to the default foreground color. Use
+    /// `Palette::resolve_bg` to resolve background colors.
+    pub fn resolve(&self, color: &Color) -> (u8, u8, u8) {
+        match *color {
+            Color::Rgb(r, g, b) => (r, g, b),
+            Color::Ansi256(n) => ansi256_to_rgb(&self.base16, n),
+            Color::Default => self.foreground,
+            c => {
+                let n = c.ansi16_index().expect("a named color");
+                self.base16[usize::from(n)]
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Reducing colors to the palettes of less capable terminals.
+//!
+//! Nearest colors are found by comparing Euclidean distances in the Oklab
+//! color space, which tracks perceived differences far better than comparing
+//! raw sRGB channels.
+
+use std::sync::OnceLock;
+
+use crate::space::{Oklab, XTERM_BASE16, ansi256_to_rgb};
+use crate::{Color, ColorLevel};
+
+/// The 16 named colors, indexed by their ANSI number.
+const ANSI16: [Color; 16] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::White,
+    Color::BrightBlack,
+    Color::BrightRed,
+    Color::BrightGreen,
+    Color::BrightYellow,
+    Color::BrightBlue,
+    Color::BrightMagenta,
+    Color::BrightCyan,
+    Color::BrightWhite,
+];
+
+/// Returns the Oklab values of every entry in the default xterm 256 color
+/// palette.
+fn palette() -> &'static [Oklab; 256] {
+    static PALETTE: OnceLock<[Oklab; 256]> = OnceLock::new();
+    PALETTE.get_or_init(|| {
+        let mut lab = [Oklab { l: 0.0, a: 0.0, b: 0.0 }; 256];
+        for (n, lab) in (0..=255).zip(lab.iter_mut()) {
+            *lab = Oklab::from_rgb(ansi256_to_rgb(&XTERM_BASE16, n));
+        }
+        lab
+    })
+}
+
+/// Returns the index in `range` of the palette entry closest to `lab`.
+///
+/// `chroma_weight` scales the `a` and `b` axes relative to lightness. The
+/// 16 color palette is so sparse that plain Oklab distances tend to map
+/// saturated colors to a gray of similar lightness, so it's matched with a
+/// larger weight to keep hues intact.
+fn nearest(
+    lab: Oklab,
+    range: std::ops::RangeInclusive<u8>,
+    chroma_weight: f64,
+) -> u8 {
+    let palette = palette();
+    let weigh = |lab: Oklab| Oklab {
+        l: lab.l,
+        a: lab.a * chroma_weight,
+        b: lab.b * chroma_weight,
+    };
+    let lab = weigh(lab);
+    range
+        .min_by(|&i, &j| {
+            let di = lab.distance_squared(weigh(palette[usize::from(i)]));
+            let dj = lab.distance_squared(weigh(palette[usize::from(j)]));
+            di.total_cmp(&dj)
+        })
+        .expect("non-empty palette range")
+}
+
+/// Returns the named color with the given ANSI number, which must be less
+/// than 16.
+pub(crate) fn ansi16_color(n: u8) -> Color {
+    ANSI16[usize::from(n)]
+}
+
+impl Color {
+    /// Returns the index of the closest color in the 256 color palette.
+    ///
+    /// Named colors and `Ansi256` colors already have an index, which is
+    /// returned as is. `Default` has no index and is treated as `White`.
+    /// `Rgb` colors are matched against the 6x6x6 color cube and the
+    /// grayscale ramp (indices `16` through `255`). The first 16 entries are
+    /// never picked for an `Rgb` color, since terminals commonly let users
+    /// redefine them.
+    pub fn to_ansi256(&self) -> u8 {
+        match *self {
+            Color::Ansi256(n) => n,
+            Color::Rgb(r, g, b) => {
+                nearest(Oklab::from_rgb((r, g, b)), 16..=255, 1.0)
+            }
+            Color::Default => Color::White.to_ansi256(),
+            _ => self.ansi16_index().expect("a named color"),
+        }
+    }
+
+    /// Returns the closest named color, for use with terminals that only
+    /// support 16 colors.
+    ///
+    /// Named colors and `Default` are returned as is, and the first 16
+    /// `Ansi256` colors map to their named equivalent. All other colors are
+    /// matched against the default xterm values of the named colors.
+    pub fn to_ansi16(&self) -> Color {
+        match *self {
+            Color::Ansi256(n) if n < 16 => ansi16_color(n),
+            Color::Ansi256(_) | Color::Rgb(..) => ansi16_color(nearest(
+                Oklab::from_rgb(self.to_rgb()),
+                0..=15,
+                2.0,
+            )),
+            named => named,
+        }
+    }
+
+    /// Returns the closest color that can be displayed at the given color
+    /// level, or `None` if the level doesn't permit any colors.
+    ///
+    /// `Rgb` colors are converted with `Color::to_ansi256` at
+    /// `ColorLevel::Ansi256`, and all non-named colors are converted with
+    /// `Color::to_ansi16` at `ColorLevel::Ansi16`. Colors that the level can
+    /// already display are returned as is.
+    pub fn to_level(&self, level: ColorLevel) -> Option<Color> {
+        match level {
+            ColorLevel::None => None,
+            ColorLevel::Ansi16 => Some(self.to_ansi16()),
+            ColorLevel::Ansi256 => match *self {
+                Color::Rgb(..) => Some(Color::Ansi256(self.to_ansi256())),
+                c => Some(c),
+            },
+            ColorLevel::TrueColor => Some(*self),
+        }
+    }
+
+    /// Returns the bright variant of one of the 8 base colors. All other
+    /// colors are returned as is.
+    pub(crate) fn to_bright(self) -> Color {
+        match self.ansi16_index() {
+            Some(n) if n < 8 => ansi16_color(n + 8),
+            _ => self,
+        }
+    }
+}
@@ -0,0 +1,341 @@
+//! Serde support for `Color`, `ColorSpec` and `ColorChoice`.
+//!
+//! All three serialize to the compact strings accepted by their `FromStr`
+//! impls. When deserializing, `Color` and `ColorSpec` additionally accept a
+//! structured form:
+//!
+//! * A `Color` may be an integer (an `Ansi256` color), a sequence of three
+//!   integers or a map with `r`, `g` and `b` keys (an `Rgb` color).
+//! * A `ColorSpec` may be a map with optional `fg`, `bg` and `ul` colors and
+//!   optional booleans for each style, e.g., `{ fg = "red", bold = true }`.
+//!   `false` turns a style off, while a missing key leaves it unset. The
+//!   `underline` key also accepts the name of an underline style, e.g.,
+//!   `underline = "curly"`.
+//!
+//! The structured forms are only accepted by human readable formats. Other
+//! formats, such as bincode, aren't self-describing and so always use the
+//! string forms.
+//!
+//! To serialize a `ColorSpec` in its structured form, use the `serde_map`
+//! module with `#[serde(with = "termcolor::serde_map")]`.
+
+use std::fmt;
+
+use serde::de::{self, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+
+use crate::{Attribute, Color, ColorChoice, ColorSpec, UnderlineStyle};
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Color, D::Error> {
+        if d.is_human_readable() {
+            d.deserialize_any(ColorVisitor)
+        } else {
+            d.deserialize_str(ColorVisitor)
+        }
+    }
+}
+
+struct ColorVisitor;
+
+impl<'de> Visitor<'de> for ColorVisitor {
+    type Value = Color;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "a color name or number, a sequence of three integers \
+             or a map with r, g and b keys"
+        )
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Color, E> {
+        v.parse().map_err(E::custom)
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Color, E> {
+        u8::try_from(v).map(Color::Ansi256).map_err(|_| {
+            E::invalid_value(de::Unexpected::Unsigned(v), &"an integer <= 255")
+        })
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Color, E> {
+        u8::try_from(v).map(Color::Ansi256).map_err(|_| {
+            E::invalid_value(de::Unexpected::Signed(v), &"an integer <= 255")
+        })
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(
+        self,
+        mut seq: A,
+    ) -> Result<Color, A::Error> {
+        let mut next = |i| {
+            seq.next_element::<u8>()?
+                .ok_or_else(|| de::Error::invalid_length(i, &"three integers"))
+        };
+        let (r, g, b) = (next(0)?, next(1)?, next(2)?);
+        if seq.next_element::<de::IgnoredAny>()?.is_some() {
+            return Err(de::Error::invalid_length(4, &"three integers"));
+        }
+        Ok(Color::Rgb(r, g, b))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(
+        self,
+        mut map: A,
+    ) -> Result<Color, A::Error> {
+        let (mut r, mut g, mut b) = (None, None, None);
+        while let Some(key) = map.next_key::<String>()? {
+            let slot = match &*key {
+                "r" => &mut r,
+                "g" => &mut g,
+                "b" => &mut b,
+                _ => {
+                    return Err(de::Error::unknown_field(
+                        &key,
+                        &["r", "g", "b"],
+                    ));
+                }
+            };
+            if slot.is_some() {
+                return Err(de::Error::custom(format!(
+                    "duplicate field `{key}`"
+                )));
+            }
+            *slot = Some(map.next_value::<u8>()?);
+        }
+        Ok(Color::Rgb(
+            r.ok_or_else(|| de::Error::missing_field("r"))?,
+            g.ok_or_else(|| de::Error::missing_field("g"))?,
+            b.ok_or_else(|| de::Error::missing_field("b"))?,
+        ))
+    }
+}
+
+/// The keys of a `ColorSpec` in its structured form.
+const SPEC_FIELDS: &[&str] = &[
+    "fg",
+    "bg",
+    "ul",
+    "bold",
+    "dimmed",
+    "italic",
+    "underline",
+    "strikethrough",
+    "blink",
+    "rapid_blink",
+    "reverse",
+    "hidden",
+    "framed",
+    "encircled",
+    "overline",
+    "intense",
+    "reset",
+];
+
+impl Serialize for ColorSpec {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&self.to_spec_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for ColorSpec {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<ColorSpec, D::Error> {
+        if d.is_human_readable() {
+            d.deserialize_any(ColorSpecVisitor)
+        } else {
+            d.deserialize_str(ColorSpecVisitor)
+        }
+    }
+}
+
+struct ColorSpecVisitor;
+
+impl<'de> Visitor<'de> for ColorSpecVisitor {
+    type Value = ColorSpec;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a color specification string or map")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<ColorSpec, E> {
+        v.parse().map_err(E::custom)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(
+        self,
+        mut map: A,
+    ) -> Result<ColorSpec, A::Error> {
+        let mut spec = ColorSpec::new();
+        let mut seen = vec![];
+        while let Some(key) = map.next_key::<String>()? {
+            if seen.contains(&key) {
+                return Err(de::Error::custom(format!(
+                    "duplicate field `{key}`"
+                )));
+            }
+            match &*key {
+                "fg" => spec.fg_color = map.next_value()?,
+                "bg" => spec.bg_color = map.next_value()?,
+                "ul" => spec.underline_color = map.next_value()?,
+                "underline" => match map.next_value()? {
+                    Underline(Some(true), style) => {
+                        spec.set_underline_style(Some(style));
+                    }
+                    Underline(state, _) => {
+                        spec.set_attr(Attribute::Underline, state);
+                    }
+                },
+                "intense" => spec.intense = map.next_value()?,
+                "reset" => spec.reset = map.next_value()?,
+                _ => {
+                    let Some(attr) = Attribute::ALL
+                        .into_iter()
+                        .find(|attr| attr.name().replace('-', "_") == key)
+                    else {
+                        return Err(de::Error::unknown_field(
+                            &key,
+                            SPEC_FIELDS,
+                        ));
+                    };
+                    spec.set_attr(attr, map.next_value()?);
+                }
+            }
+            seen.push(key);
+        }
+        Ok(spec)
+    }
+}
+
+/// The value of the `underline` key of a `ColorSpec` in its structured
+/// form: either an optional boolean or the name of an underline style.
+struct Underline(Option<bool>, UnderlineStyle);
+
+impl<'de> Deserialize<'de> for Underline {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Underline, D::Error> {
+        if d.is_human_readable() {
+            d.deserialize_any(UnderlineVisitor)
+        } else {
+            d.deserialize_str(UnderlineVisitor)
+        }
+    }
+}
+
+struct UnderlineVisitor;
+
+impl<'de> Visitor<'de> for UnderlineVisitor {
+    type Value = Underline;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a boolean or an underline style")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Underline, E> {
+        Ok(Underline(Some(v), UnderlineStyle::Single))
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Underline, E> {
+        Ok(Underline(None, UnderlineStyle::Single))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Underline, E> {
+        self.visit_none()
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Underline, E> {
+        match UnderlineStyle::from_name(v) {
+            Some(style) => Ok(Underline(Some(true), style)),
+            None => Err(E::invalid_value(
+                de::Unexpected::Str(v),
+                &"one of single, double, curly, dotted or dashed",
+            )),
+        }
+    }
+}
+
+impl Serialize for ColorChoice {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(match *self {
+            ColorChoice::Always => "always",
+            ColorChoice::AlwaysAnsi => "always-ansi",
+            ColorChoice::Auto => "auto",
+            ColorChoice::Never => "never",
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for ColorChoice {
+    fn deserialize<D: Deserializer<'de>>(
+        d: D,
+    ) -> Result<ColorChoice, D::Error> {
+        String::deserialize(d)?.parse().map_err(de::Error::custom)
+    }
+}
+
+/// Serializes a `ColorSpec` in its structured form.
+///
+/// Use this with `#[serde(with = "termcolor::serde_map")]` on a `ColorSpec`
+/// field. Styles that are unset are omitted, as is `reset` when it's
+/// enabled. Deserializing accepts both the structured and the string form.
+/// Formats that aren't human readable use the string form in both
+/// directions.
+pub mod serde_map {
+    use serde::ser::{SerializeMap, Serializer};
+    use serde::{Deserialize, Deserializer};
+
+    use crate::{Attribute, ColorSpec, UnderlineStyle};
+
+    /// Serialize a `ColorSpec` as a map.
+    pub fn serialize<S: Serializer>(
+        spec: &ColorSpec,
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        if !s.is_human_readable() {
+            return s.serialize_str(&spec.to_spec_string());
+        }
+        let mut map = s.serialize_map(None)?;
+        if let Some(c) = spec.fg() {
+            map.serialize_entry("fg", c)?;
+        }
+        if let Some(c) = spec.bg() {
+            map.serialize_entry("bg", c)?;
+        }
+        if let Some(c) = spec.underline_color() {
+            map.serialize_entry("ul", c)?;
+        }
+        for attr in Attribute::ALL {
+            let key = attr.name().replace('-', "_");
+            match (spec.attr(attr), spec.underline_style()) {
+                (None, _) => {}
+                (Some(true), Some(style))
+                    if attr == Attribute::Underline
+                        && style != UnderlineStyle::Single =>
+                {
+                    map.serialize_entry(&key, style.name())?;
+                }
+                (Some(yes), _) => map.serialize_entry(&key, &yes)?,
+            }
+        }
+        if spec.intense() {
+            map.serialize_entry("intense", &true)?;
+        }
+        if !spec.reset() {
+            map.serialize_entry("reset", &false)?;
+        }
+        map.end()
+    }
+
+    /// Deserialize a `ColorSpec` from either its string or map form.
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        d: D,
+    ) -> Result<ColorSpec, D::Error> {
+        ColorSpec::deserialize(d)
+    }
+}
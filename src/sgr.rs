@@ -0,0 +1,399 @@
+//! Decoding SGR escape sequences back into colors and color specifications.
+
+use crate::quantize::ansi16_color;
+use crate::{Attribute, Color, ColorSpec, UnderlineStyle};
+
+/// A single SGR parameter along with its colon separated sub-parameters,
+/// e.g., `4:3`. Empty (sub-)parameters are `None`, which terminals treat as
+/// `0`.
+type Param = Vec<Option<u16>>;
+
+/// The part of the text that a color applies to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Layer {
+    Fg,
+    Bg,
+    Underline,
+}
+
+impl ColorSpec {
+    /// Decodes SGR escape sequences, such as `\x1B[1;38;5;208;48;2;0;0;0m`,
+    /// into the color specification that they describe.
+    ///
+    /// `sgr` must consist of zero or more complete SGR sequences, each of
+    /// the form `ESC [ params m`. Parameters may be separated by
+    /// semicolons, and the extended colors `38`, `48` and `58` may also be
+    /// written in the colon separated form, e.g., `38:2::255:136:0`.
+    /// Parameters that have no equivalent in a `ColorSpec` are ignored.
+    /// `None` is returned if `sgr` contains anything other than SGR
+    /// sequences or if an extended color is malformed.
+    ///
+    /// This is the inverse of `ansi::ansi_spec`:
+    ///
+    /// * Reset is enabled if and only if the sequences contain a reset
+    ///   (`0` or an empty sequence), which discards all prior settings.
+    /// * A bold code (`1`) that follows a foreground color is decoded as
+    ///   intensity, since that's how `ansi_spec` writes intense colors.
+    /// * The codes `22`, `25` and `54` each turn off two attributes, e.g.,
+    ///   `22` turns off both bold and dimmed text, so both of them are
+    ///   decoded as turned off.
+    /// * Underline colors from the first 16 entries of the 256 color palette
+    ///   are decoded as named colors, since `ansi_spec` writes named
+    ///   underline colors with their palette index.
+    ///
+    /// Thus, `ColorSpec::from_sgr` recovers every specification written by
+    /// `ansi_spec`, except for these, which SGR can't tell apart from other
+    /// specifications:
+    ///
+    /// * Turning off only one attribute of a pair that shares an off code,
+    ///   e.g., dimmed text, is decoded as turning off both of them.
+    /// * An `Ansi256` underline color from the first 16 entries of the
+    ///   palette is decoded as the equivalent named color.
+    /// * Intensity without a foreground color isn't written at all.
+    pub fn from_sgr(sgr: &[u8]) -> Option<ColorSpec> {
+        let mut spec = ColorSpec::new();
+        spec.set_reset(false);
+        let mut rest = sgr;
+        while !rest.is_empty() {
+            let body = rest.strip_prefix(b"\x1B[")?;
+            let end = body.iter().position(|&b| b == b'm')?;
+            decode(&mut spec, &parse_params(&body[..end])?)?;
+            rest = &body[end + 1..];
+        }
+        Some(spec)
+    }
+}
+
+impl Color {
+    /// Decodes the SGR parameters of a single color, without the
+    /// surrounding `ESC [` and `m`.
+    ///
+    /// This accepts foreground, background and underline colors alike, and
+    /// returns the color regardless of which of them it's for:
+    ///
+    /// * `30` through `37`, `90` through `97`, `40` through `47` and `100`
+    ///   through `107` are named colors.
+    /// * `39`, `49` and `59` are `Color::Default`.
+    /// * `38;5;n` is an `Ansi256` color and `38;2;r;g;b` is an `Rgb` color,
+    ///   along with the same forms for `48` and `58`. The colon separated
+    ///   forms `38:5:n`, `38:2:r:g:b` and `38:2:id:r:g:b` (where `id` is an
+    ///   ignored color space identifier, which is usually empty) are
+    ///   accepted too.
+    ///
+    /// `None` is returned if `params` is anything else.
+    pub fn from_sgr_params(params: &[u8]) -> Option<Color> {
+        let params = parse_params(params)?;
+        match color_at(&params)? {
+            (_, color, used) if used == params.len() => Some(color),
+            _ => None,
+        }
+    }
+}
+
+/// Decodes the SGR parameters of a style in a `key=SGR` database, such as
+/// `LS_COLORS`, into a specification that's written after a reset.
+///
+/// The programs that read such databases write the parameters as given, so
+/// a bold code after a foreground color is decoded as bold text rather than
+/// intensity, which would also brighten the background.
+pub(crate) fn style_from_params(params: &str) -> Option<ColorSpec> {
+    let mut spec = ColorSpec::from_sgr(format!("\x1B[{params}m").as_bytes())?;
+    if spec.intense() {
+        spec.set_intense(false);
+        spec.set_bold(true);
+    }
+    spec.set_reset(true);
+    Some(spec)
+}
+
+/// Splits the parameters of an SGR sequence on semicolons and colons.
+///
+/// Returns `None` if there's anything besides digits and separators, or if
+/// a number is too large.
+fn parse_params(params: &[u8]) -> Option<Vec<Param>> {
+    params
+        .split(|&b| b == b';')
+        .map(|param| {
+            param
+                .split(|&b| b == b':')
+                .map(|sub| {
+                    if sub.is_empty() {
+                        return Some(None);
+                    }
+                    if !sub.iter().all(u8::is_ascii_digit) {
+                        return None;
+                    }
+                    std::str::from_utf8(sub).ok()?.parse().ok().map(Some)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Applies the parameters of a single SGR sequence to `spec`.
+///
+/// Returns `None` if an extended color is malformed.
+fn decode(spec: &mut ColorSpec, params: &[Param]) -> Option<()> {
+    let mut i = 0;
+    while i < params.len() {
+        let param = &params[i];
+        let code = param[0].unwrap_or(0);
+        match code {
+            0 => *spec = ColorSpec::new(),
+            1 if spec.fg().is_some() => {
+                spec.set_intense(true);
+            }
+            4 => match param.get(1).map(|sub| sub.unwrap_or(0)) {
+                None | Some(1) => {
+                    spec.set_underline_style(Some(UnderlineStyle::Single));
+                }
+                Some(0) => {
+                    spec.set_attr(Attribute::Underline, Some(false));
+                }
+                Some(2) => {
+                    spec.set_underline_style(Some(UnderlineStyle::Double));
+                }
+                Some(3) => {
+                    spec.set_underline_style(Some(UnderlineStyle::Curly));
+                }
+                Some(4) => {
+                    spec.set_underline_style(Some(UnderlineStyle::Dotted));
+                }
+                Some(5) => {
+                    spec.set_underline_style(Some(UnderlineStyle::Dashed));
+                }
+                Some(_) => {}
+            },
+            21 => {
+                spec.set_underline_style(Some(UnderlineStyle::Double));
+            }
+            30..=39 | 40..=49 | 58 | 59 | 90..=97 | 100..=107 => {
+                let (layer, color, used) = color_at(&params[i..])?;
+                match layer {
+                    Layer::Fg => spec.set_fg(Some(color)),
+                    Layer::Bg => spec.set_bg(Some(color)),
+                    Layer::Underline => {
+                        let color = match color {
+                            Color::Ansi256(n) if n < 16 => ansi16_color(n),
+                            color => color,
+                        };
+                        spec.set_underline_color(Some(color))
+                    }
+                };
+                i += used;
+                continue;
+            }
+            code => {
+                let code = code.to_string();
+                for attr in Attribute::ALL {
+                    if attr.on_sgr() == code {
+                        spec.set_attr(attr, Some(true));
+                    } else if attr.off_sgr() == code {
+                        spec.set_attr(attr, Some(false));
+                    }
+                }
+                // Intense colors are written as bold text, so they're
+                // turned off along with it.
+                if code == Attribute::Bold.off_sgr() {
+                    spec.set_intense(false);
+                }
+            }
+        }
+        i += 1;
+    }
+    Some(())
+}
+
+/// Decodes the color at the start of `params`.
+///
+/// Returns the layer that the color applies to, the color and the number
+/// of parameters that it spans, or `None` if `params` doesn't start with a
+/// well-formed color.
+fn color_at(params: &[Param]) -> Option<(Layer, Color, usize)> {
+    let param = params.first()?;
+    let code = param[0].unwrap_or(0);
+    let named = |n: u16| ansi16_color(n as u8);
+    let (layer, color, used) = match code {
+        30..=37 => (Layer::Fg, named(code - 30), 1),
+        90..=97 => (Layer::Fg, named(code - 90 + 8), 1),
+        40..=47 => (Layer::Bg, named(code - 40), 1),
+        100..=107 => (Layer::Bg, named(code - 100 + 8), 1),
+        39 => (Layer::Fg, Color::Default, 1),
+        49 => (Layer::Bg, Color::Default, 1),
+        59 => (Layer::Underline, Color::Default, 1),
+        38 | 48 | 58 => {
+            let layer = match code {
+                38 => Layer::Fg,
+                48 => Layer::Bg,
+                _ => Layer::Underline,
+            };
+            if param.len() > 1 {
+                (layer, extended_color(&param[1..], true)?.0, 1)
+            } else {
+                let args: Vec<Option<u16>> =
+                    params[1..].iter().map(|p| p[0]).collect();
+                let (color, used) = extended_color(&args, false)?;
+                (layer, color, 1 + used)
+            }
+        }
+        _ => return None,
+    };
+    Some((layer, color, used))
+}
+
+/// Decodes the arguments of an extended color, i.e., `5;n` or `2;r;g;b`,
+/// and returns the color along with the number of arguments that it spans.
+///
+/// In the colon separated form, an RGB color may also be written with a
+/// color space identifier, as in `2:id:r:g:b`, which is ignored.
+fn extended_color(
+    args: &[Option<u16>],
+    colon: bool,
+) -> Option<(Color, usize)> {
+    let byte = |arg: &Option<u16>| u8::try_from(arg.unwrap_or(0)).ok();
+    match args.first()?.unwrap_or(0) {
+        5 => Some((Color::Ansi256(byte(args.get(1)?)?), 2)),
+        2 => {
+            let rgb = if colon && args.len() >= 5 {
+                &args[2..5]
+            } else {
+                args.get(1..4)?
+            };
+            let color =
+                Color::Rgb(byte(&rgb[0])?, byte(&rgb[1])?, byte(&rgb[2])?);
+            Some((color, 4))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ansi::ansi_spec;
+
+    /// Returns a deterministic assortment of color specifications, covering
+    /// every kind of color and every state of each attribute.
+    fn specs(count: usize) -> Vec<ColorSpec> {
+        let colors = [
+            None,
+            Some(Color::Default),
+            Some(Color::Blue),
+            Some(Color::Ansi256(3)),
+            Some(Color::Ansi256(12)),
+            Some(Color::Ansi256(200)),
+            Some(Color::Rgb(0, 128, 255)),
+        ];
+        let styles = [
+            UnderlineStyle::Single,
+            UnderlineStyle::Double,
+            UnderlineStyle::Curly,
+            UnderlineStyle::Dotted,
+            UnderlineStyle::Dashed,
+        ];
+        let mut seed = 0x9e37_79b9_7f4a_7c15u64;
+        let mut next = |n: usize| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % n as u64) as usize
+        };
+        (0..count)
+            .map(|_| {
+                let mut spec = ColorSpec::new();
+                for attr in Attribute::ALL {
+                    spec.set_attr(
+                        attr,
+                        [None, Some(true), Some(false)][next(3)],
+                    );
+                }
+                if spec.underline() {
+                    spec.set_underline_style(Some(styles[next(styles.len())]));
+                }
+                spec.set_fg(colors[next(colors.len())])
+                    .set_bg(colors[next(colors.len())])
+                    .set_underline_color(colors[next(colors.len())])
+                    .set_intense(next(2) == 0)
+                    .set_reset(next(2) == 0);
+                spec
+            })
+            .collect()
+    }
+
+    /// Returns what `from_sgr` decodes the output of `ansi_spec` for the
+    /// given specification as, according to the documented exceptions.
+    fn expected(spec: &ColorSpec) -> ColorSpec {
+        let mut expected = *spec;
+        for attr in Attribute::ALL {
+            if spec.attr(attr) != Some(false) {
+                continue;
+            }
+            for other in Attribute::ALL {
+                if other.off_sgr() == attr.off_sgr()
+                    && spec.attr(other).is_none()
+                {
+                    expected.set_attr(other, Some(false));
+                }
+            }
+        }
+        if let Some(&Color::Ansi256(n)) = spec.underline_color()
+            && n < 16
+        {
+            expected.set_underline_color(Some(ansi16_color(n)));
+        }
+        if spec.fg().is_none() {
+            expected.set_intense(false);
+        }
+        expected
+    }
+
+    fn decode_spec(spec: &ColorSpec) -> Option<ColorSpec> {
+        let mut sgr = vec![];
+        ansi_spec(&mut sgr, spec).unwrap();
+        ColorSpec::from_sgr(&sgr)
+    }
+
+    #[test]
+    fn round_trip() {
+        for spec in specs(5000) {
+            assert_eq!(decode_spec(&spec), Some(expected(&spec)), "{spec:?}");
+        }
+    }
+
+    #[test]
+    fn round_trip_exceptions() {
+        let mut spec = ColorSpec::new();
+        spec.set_attr(Attribute::Dimmed, Some(false));
+        let mut both = ColorSpec::new();
+        both.set_attr(Attribute::Bold, Some(false))
+            .set_attr(Attribute::Dimmed, Some(false));
+        assert_eq!(decode_spec(&spec), Some(both));
+
+        let mut spec = ColorSpec::new();
+        spec.set_underline_color(Some(Color::Ansi256(1)));
+        let mut named = ColorSpec::new();
+        named.set_underline_color(Some(Color::Red));
+        assert_eq!(decode_spec(&spec), Some(named));
+
+        let mut spec = ColorSpec::new();
+        spec.set_intense(true);
+        assert_eq!(decode_spec(&spec), Some(ColorSpec::new()));
+    }
+
+    #[test]
+    fn shared_off_codes() {
+        let decode = |sgr: &[u8]| ColorSpec::from_sgr(sgr).unwrap();
+        for (sgr, first, second) in [
+            (b"\x1B[22m", Attribute::Bold, Attribute::Dimmed),
+            (b"\x1B[25m", Attribute::Blink, Attribute::RapidBlink),
+            (b"\x1B[54m", Attribute::Framed, Attribute::Encircled),
+        ] {
+            let spec = decode(sgr);
+            assert_eq!(spec.attr(first), Some(false));
+            assert_eq!(spec.attr(second), Some(false));
+        }
+        let spec = decode(b"\x1B[22;2m");
+        assert_eq!(spec.attr(Attribute::Bold), Some(false));
+        assert_eq!(spec.attr(Attribute::Dimmed), Some(true));
+    }
+}
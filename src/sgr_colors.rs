@@ -0,0 +1,174 @@
+//! Styles from `key=SGR` environment variables such as `GREP_COLORS` and
+//! `GCC_COLORS`.
+
+use std::env;
+
+use crate::ColorSpec;
+use crate::sgr::style_from_params;
+
+/// The default value of `GREP_COLORS`, as documented by GNU grep.
+const GREP_DEFAULTS: &str =
+    "ms=01;31:mc=01;31:sl=:cx=:fn=35:ln=32:bn=32:se=36";
+
+/// The default value of `GCC_COLORS`, as documented by GCC.
+const GCC_DEFAULTS: &str = "error=01;31:warning=01;35:note=01;36:\
+    range1=32:range2=34:locus=01:quote=01:path=01;36:fixit-insert=32:\
+    fixit-delete=31:diff-filename=01:diff-hunk=32:diff-delete=31:\
+    diff-insert=32:type-diff=01;32:fnname=01;32:targs=35";
+
+/// A set of styles keyed by name, in the `key=SGR:key=SGR` format used by
+/// environment variables such as `GREP_COLORS` and `GCC_COLORS`.
+///
+/// Each entry maps a key to SGR parameters, e.g., `error=01;31`. An empty
+/// value, as in `sl=`, sets a style that sets nothing. Entries without a
+/// value, such as `ne` in `GREP_COLORS`, are boolean flags.
+///
+/// Use `SgrPreset` to read the variables of specific programs along with
+/// their defaults.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SgrColors {
+    styles: Vec<(String, ColorSpec)>,
+    flags: Vec<String>,
+}
+
+impl SgrColors {
+    /// Create a new set without any styles or flags.
+    pub fn new() -> SgrColors {
+        SgrColors::default()
+    }
+
+    /// Parse a set of styles in the `key=SGR:key=SGR` format.
+    ///
+    /// Later entries override earlier ones with the same key. Entries whose
+    /// value isn't a valid list of SGR parameters are ignored.
+    pub fn parse(spec: &str) -> SgrColors {
+        let mut colors = SgrColors::new();
+        colors.extend_from_str(spec);
+        colors
+    }
+
+    /// Returns the style with the given key, if it's set.
+    pub fn get(&self, key: &str) -> Option<&ColorSpec> {
+        self.styles.iter().find(|(k, _)| k == key).map(|(_, spec)| spec)
+    }
+
+    /// Set or unset the style with the given key.
+    pub fn set(
+        &mut self,
+        key: &str,
+        spec: Option<ColorSpec>,
+    ) -> &mut SgrColors {
+        let pos = self.styles.iter().position(|(k, _)| k == key);
+        match (pos, spec) {
+            (Some(i), Some(spec)) => self.styles[i].1 = spec,
+            (None, Some(spec)) => self.styles.push((key.to_string(), spec)),
+            (Some(i), None) => {
+                self.styles.remove(i);
+            }
+            (None, None) => {}
+        }
+        self
+    }
+
+    /// Returns true if the boolean flag with the given key is set.
+    pub fn flag(&self, key: &str) -> bool {
+        self.flags.iter().any(|k| k == key)
+    }
+
+    /// Set or unset the boolean flag with the given key.
+    pub fn set_flag(&mut self, key: &str, yes: bool) -> &mut SgrColors {
+        let set = self.flag(key);
+        if yes && !set {
+            self.flags.push(key.to_string());
+        } else if !yes && set {
+            self.flags.retain(|k| k != key);
+        }
+        self
+    }
+
+    /// Returns an iterator over the keys and styles of this set, in the
+    /// order in which they were first set.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ColorSpec)> {
+        self.styles.iter().map(|(key, spec)| (&**key, spec))
+    }
+
+    /// Parse the entries of `spec` on top of this set.
+    fn extend_from_str(&mut self, spec: &str) {
+        for entry in spec.split(':').filter(|entry| !entry.is_empty()) {
+            match entry.split_once('=') {
+                None => {
+                    self.set_flag(entry, true);
+                }
+                Some((key, value)) => {
+                    if let Some(spec) = style_from_params(value) {
+                        self.set(key, Some(spec));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A program whose `key=SGR` environment variable `SgrColors` can read.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SgrPreset {
+    /// GNU grep, which reads `GREP_COLORS`.
+    ///
+    /// The keys are `ms` and `mc` (matched text in selected and context
+    /// lines), `sl` and `cx` (selected and context lines), `fn` (file
+    /// names), `ln` (line numbers), `bn` (byte offsets) and `se`
+    /// (separators). `mt` sets both `ms` and `mc`. The flags are `rv` and
+    /// `ne`.
+    Grep,
+    /// GCC, which reads `GCC_COLORS`.
+    ///
+    /// The keys include `error`, `warning`, `note`, `range1`, `range2`,
+    /// `locus`, `quote`, `path` and `fnname`, among others. Setting
+    /// `GCC_COLORS` to an empty value disables colors.
+    Gcc,
+}
+
+impl SgrPreset {
+    /// Returns the name of the environment variable of this program.
+    pub fn env_var(self) -> &'static str {
+        match self {
+            SgrPreset::Grep => "GREP_COLORS",
+            SgrPreset::Gcc => "GCC_COLORS",
+        }
+    }
+
+    /// Returns the styles that this program uses by default.
+    pub fn defaults(self) -> SgrColors {
+        SgrColors::parse(match self {
+            SgrPreset::Grep => GREP_DEFAULTS,
+            SgrPreset::Gcc => GCC_DEFAULTS,
+        })
+    }
+
+    /// Parse a value of this program's environment variable on top of its
+    /// defaults, the way the program does.
+    pub fn parse(self, spec: &str) -> SgrColors {
+        if self == SgrPreset::Gcc && spec.is_empty() {
+            return SgrColors::new();
+        }
+        let mut colors = self.defaults();
+        for entry in spec.split(':') {
+            match entry.split_once('=') {
+                Some(("mt", value)) if self == SgrPreset::Grep => {
+                    colors.extend_from_str(&format!("ms={value}:mc={value}"));
+                }
+                _ => colors.extend_from_str(entry),
+            }
+        }
+        colors
+    }
+
+    /// Returns the styles configured by this program's environment
+    /// variable, or its defaults if the variable is unset.
+    pub fn from_env(self) -> SgrColors {
+        match env::var(self.env_var()) {
+            Ok(v) => self.parse(&v),
+            Err(_) => self.defaults(),
+        }
+    }
+}
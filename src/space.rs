@@ -0,0 +1,296 @@
+//! Conversions between `Color` and other color spaces.
+//!
+//! All conversions go through 8-bit sRGB, since that's what a terminal
+//! ultimately receives. Named and `Ansi256` colors are resolved to RGB using
+//! the default xterm palette.
+
+use crate::{Color, Palette};
+
+/// The default xterm RGB values of the 16 base ANSI colors, indexed by their
+/// ANSI number.
+pub(crate) const XTERM_BASE16: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// The channel values used by the 6x6x6 color cube in the 256 color
+/// palette.
+pub(crate) const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Returns the RGB value of an entry in the 256 color palette, using the
+/// given RGB values for the first 16 entries.
+pub(crate) fn ansi256_to_rgb(
+    base16: &[(u8, u8, u8); 16],
+    n: u8,
+) -> (u8, u8, u8) {
+    match n {
+        0..=15 => base16[usize::from(n)],
+        16..=231 => {
+            let i = n - 16;
+            (
+                CUBE_LEVELS[usize::from(i / 36)],
+                CUBE_LEVELS[usize::from((i / 6) % 6)],
+                CUBE_LEVELS[usize::from(i % 6)],
+            )
+        }
+        232..=255 => {
+            let v = 8 + 10 * (n - 232);
+            (v, v, v)
+        }
+    }
+}
+
+/// A color in the Oklab color space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Oklab {
+    pub(crate) l: f64,
+    pub(crate) a: f64,
+    pub(crate) b: f64,
+}
+
+impl Oklab {
+    /// Converts an 8-bit sRGB color to Oklab.
+    pub(crate) fn from_rgb((r, g, b): (u8, u8, u8)) -> Oklab {
+        let (r, g, b) = (to_linear(r), to_linear(g), to_linear(b));
+        let l = 0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b;
+        let m = 0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b;
+        let s = 0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b;
+        let (l, m, s) = (l.cbrt(), m.cbrt(), s.cbrt());
+        Oklab {
+            l: 0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+            a: 1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+            b: 0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+        }
+    }
+
+    /// Converts this color to 8-bit sRGB, clipping any channels that fall
+    /// outside of the sRGB gamut.
+    pub(crate) fn to_rgb(self) -> (u8, u8, u8) {
+        let l = self.l + 0.3963377774 * self.a + 0.2158037573 * self.b;
+        let m = self.l - 0.1055613458 * self.a - 0.0638541728 * self.b;
+        let s = self.l - 0.0894841775 * self.a - 1.2914855480 * self.b;
+        let (l, m, s) = (l * l * l, m * m * m, s * s * s);
+        let r = 4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s;
+        let g = -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s;
+        let b = -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s;
+        (from_linear(r), from_linear(g), from_linear(b))
+    }
+
+    /// Returns the squared Euclidean distance between two Oklab colors.
+    pub(crate) fn distance_squared(self, other: Oklab) -> f64 {
+        let (dl, da, db) =
+            (self.l - other.l, self.a - other.a, self.b - other.b);
+        dl * dl + da * da + db * db
+    }
+}
+
+/// Converts an 8-bit sRGB channel to linear light in `[0, 1]`.
+pub(crate) fn to_linear(c: u8) -> f64 {
+    let c = f64::from(c) / 255.0;
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+/// Converts a linear light value to an 8-bit sRGB channel, clamping it to
+/// `[0, 1]` first.
+pub(crate) fn from_linear(c: f64) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let c = if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    to_u8(c)
+}
+
+/// Converts a value in `[0, 1]` to a channel in `[0, 255]`.
+fn to_u8(c: f64) -> u8 {
+    (c.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Normalizes a hue in degrees to `[0, 360)`.
+fn normalize_hue(h: f64) -> f64 {
+    let h = h.rem_euclid(360.0);
+    // `rem_euclid` can round up to exactly 360 for tiny negative inputs.
+    if h >= 360.0 { 0.0 } else { h }
+}
+
+/// Returns the RGB value of a color with the given hue, chroma and offset,
+/// which is the common tail of the HSL and HSV conversions.
+fn hue_to_rgb(h: f64, c: f64, m: f64) -> (u8, u8, u8) {
+    let h = normalize_hue(h) / 60.0;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u8 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    (to_u8(r + m), to_u8(g + m), to_u8(b + m))
+}
+
+/// Returns the hue in degrees, the maximum channel, the minimum channel and
+/// the chroma of an RGB color, with channels scaled to `[0, 1]`.
+fn rgb_hue((r, g, b): (u8, u8, u8)) -> (f64, f64, f64, f64) {
+    let (r, g, b) =
+        (f64::from(r) / 255.0, f64::from(g) / 255.0, f64::from(b) / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let c = max - min;
+    let h = if c == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / c).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / c + 2.0)
+    } else {
+        60.0 * ((r - g) / c + 4.0)
+    };
+    (normalize_hue(h), max, min, c)
+}
+
+impl Color {
+    /// Returns the RGB value of this color.
+    ///
+    /// `Rgb` colors are returned as is. Named and `Ansi256` colors are
+    /// resolved using the default xterm palette, which may differ from the
+    /// palette of the terminal that the color is eventually displayed on.
+    ///
+    /// `Default` has no fixed value, and is resolved as if it were `White`.
+    ///
+    /// Use `Palette::resolve` to resolve colors with a different palette.
+    pub fn to_rgb(&self) -> (u8, u8, u8) {
+        Palette::xterm().resolve(self)
+    }
+
+    /// Returns the index of a named color in the 16 color ANSI palette, or
+    /// `None` for `Ansi256`, `Rgb` and `Default` colors.
+    pub(crate) fn ansi16_index(&self) -> Option<u8> {
+        Some(match *self {
+            Color::Black => 0,
+            Color::Red => 1,
+            Color::Green => 2,
+            Color::Yellow => 3,
+            Color::Blue => 4,
+            Color::Magenta => 5,
+            Color::Cyan => 6,
+            Color::White => 7,
+            Color::BrightBlack => 8,
+            Color::BrightRed => 9,
+            Color::BrightGreen => 10,
+            Color::BrightYellow => 11,
+            Color::BrightBlue => 12,
+            Color::BrightMagenta => 13,
+            Color::BrightCyan => 14,
+            Color::BrightWhite => 15,
+            Color::Ansi256(_) | Color::Rgb(..) | Color::Default => {
+                return None;
+            }
+        })
+    }
+
+    /// Create an `Rgb` color from HSL components.
+    ///
+    /// The hue is given in degrees and is normalized to `[0, 360)`. The
+    /// saturation and lightness are clamped to `[0, 1]`.
+    pub fn from_hsl(h: f64, s: f64, l: f64) -> Color {
+        let (s, l) = (s.clamp(0.0, 1.0), l.clamp(0.0, 1.0));
+        let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        let (r, g, b) = hue_to_rgb(h, c, l - c / 2.0);
+        Color::Rgb(r, g, b)
+    }
+
+    /// Returns the HSL components of this color.
+    ///
+    /// The hue is in degrees in the range `[0, 360)`, and the saturation and
+    /// lightness are in the range `[0, 1]`. The hue of a gray is `0`.
+    pub fn to_hsl(&self) -> (f64, f64, f64) {
+        let (h, max, min, c) = rgb_hue(self.to_rgb());
+        let l = (max + min) / 2.0;
+        let s = if c == 0.0 { 0.0 } else { c / (1.0 - (2.0 * l - 1.0).abs()) };
+        (h, s, l)
+    }
+
+    /// Create an `Rgb` color from HSV components.
+    ///
+    /// The hue is given in degrees and is normalized to `[0, 360)`. The
+    /// saturation and value are clamped to `[0, 1]`.
+    pub fn from_hsv(h: f64, s: f64, v: f64) -> Color {
+        let (s, v) = (s.clamp(0.0, 1.0), v.clamp(0.0, 1.0));
+        let c = v * s;
+        let (r, g, b) = hue_to_rgb(h, c, v - c);
+        Color::Rgb(r, g, b)
+    }
+
+    /// Returns the HSV components of this color.
+    ///
+    /// The hue is in degrees in the range `[0, 360)`, and the saturation and
+    /// value are in the range `[0, 1]`. The hue of a gray is `0`.
+    pub fn to_hsv(&self) -> (f64, f64, f64) {
+        let (h, max, _, c) = rgb_hue(self.to_rgb());
+        let s = if max == 0.0 { 0.0 } else { c / max };
+        (h, s, max)
+    }
+
+    /// Create an `Rgb` color from OKLCH components.
+    ///
+    /// The lightness is clamped to `[0, 1]`, the chroma is clamped to be
+    /// non-negative and the hue is given in degrees. Colors outside of the
+    /// sRGB gamut are clipped channel by channel.
+    pub fn from_oklch(l: f64, c: f64, h: f64) -> Color {
+        let (l, c, h) = (l.clamp(0.0, 1.0), c.max(0.0), h.to_radians());
+        let (r, g, b) = Oklab { l, a: c * h.cos(), b: c * h.sin() }.to_rgb();
+        Color::Rgb(r, g, b)
+    }
+
+    /// Returns the OKLCH components of this color.
+    ///
+    /// The lightness is in the range `[0, 1]`, the chroma is non-negative
+    /// (and at most about `0.32` for sRGB colors) and the hue is in degrees
+    /// in the range `[0, 360)`. The chroma and hue of a gray are `0`.
+    pub fn to_oklch(&self) -> (f64, f64, f64) {
+        let lab = Oklab::from_rgb(self.to_rgb());
+        let c = lab.a.hypot(lab.b);
+        // Grays have a tiny amount of chroma due to rounding, which would
+        // otherwise produce an arbitrary hue.
+        if c < 1e-4 {
+            return (lab.l, 0.0, 0.0);
+        }
+        let h = normalize_hue(lab.b.atan2(lab.a).to_degrees());
+        (lab.l, c, h)
+    }
+
+    /// Returns the color a fraction `t` of the way from this color to
+    /// `other`.
+    ///
+    /// The interpolation is done in the Oklab color space, which keeps
+    /// perceived lightness and hue changes even, and always returns an `Rgb`
+    /// color. `t` is clamped to `[0, 1]`.
+    pub fn lerp(&self, other: &Color, t: f64) -> Color {
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+        let (from, to) =
+            (Oklab::from_rgb(self.to_rgb()), Oklab::from_rgb(other.to_rgb()));
+        let (r, g, b) = Oklab {
+            l: from.l + (to.l - from.l) * t,
+            a: from.a + (to.a - from.a) * t,
+            b: from.b + (to.b - from.b) * t,
+        }
+        .to_rgb();
+        Color::Rgb(r, g, b)
+    }
+}
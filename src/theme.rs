@@ -0,0 +1,682 @@
+//! Named styles that are loaded from configuration text.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use crate::types::{closest, split_spec};
+use crate::{
+    ColorLevel, ColorSpec, ColorSpecParseError, HyperlinkSpec, WriteColor,
+};
+
+/// A set of named styles, such as `error`, `path` or `line-number`.
+///
+/// A theme is usually loaded from text with one definition per line:
+///
+/// ```text
+/// # Comments take up a whole line.
+/// error = fg:red,bold
+/// error.code = error,underline
+/// line-number = fg:green
+/// ```
+///
+/// Each definition is a comma separated list of items. An item is either a
+/// setting of a color specification, as accepted by
+/// `ColorSpec::parse_strict`, or the name of another style, whose settings
+/// are inherited. Items are merged from left to right with
+/// `ColorSpec::apply`, so later items take precedence over earlier ones.
+/// Bare colors aren't allowed, since they're easily confused with names:
+/// write `fg:red` instead of `red`.
+///
+/// Names consist of ASCII letters, digits, `-`, `_` and `.`, and can't be
+/// settings themselves, such as `bold` or `red`. A dot doesn't imply
+/// inheritance, so `error.code` only inherits from `error` if it says so.
+///
+/// Use `Themed` to give a writer a theme, so that it can set styles by
+/// name with `WriteColor::set_style`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Theme {
+    styles: BTreeMap<String, ColorSpec>,
+}
+
+impl Theme {
+    /// Create a new theme without any styles.
+    pub fn new() -> Theme {
+        Theme::default()
+    }
+
+    /// Returns the style with the given name, if it's defined.
+    pub fn get(&self, name: &str) -> Option<&ColorSpec> {
+        self.styles.get(name)
+    }
+
+    /// Define the style with the given name, replacing any existing style
+    /// with that name.
+    ///
+    /// Styles that were loaded before and inherited from a style with this
+    /// name are unaffected.
+    pub fn set(&mut self, name: &str, spec: ColorSpec) -> &mut Theme {
+        self.styles.insert(name.to_string(), spec);
+        self
+    }
+
+    /// Remove the style with the given name and return it, if it was
+    /// defined.
+    pub fn remove(&mut self, name: &str) -> Option<ColorSpec> {
+        self.styles.remove(name)
+    }
+
+    /// Returns an iterator over the names and styles of this theme, sorted
+    /// by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ColorSpec)> {
+        self.styles.iter().map(|(name, spec)| (&**name, spec))
+    }
+
+    /// Returns the number of styles in this theme.
+    pub fn len(&self) -> usize {
+        self.styles.len()
+    }
+
+    /// Returns true if this theme has no styles.
+    pub fn is_empty(&self) -> bool {
+        self.styles.is_empty()
+    }
+
+    /// The names of the semantic styles that the built-in themes define.
+    ///
+    /// * `error`, `warning`, `info`, `note`, `success` and `hint` are for
+    ///   diagnostics and their labels.
+    /// * `emphasis` is for text that should stand out, without implying
+    ///   anything.
+    /// * `code`, `path` and `link` are for source code, file paths and URLs
+    ///   that appear in prose.
+    /// * `muted` is for secondary text, such as line numbers or timestamps.
+    pub const SEMANTIC: [&'static str; 11] = [
+        "error", "warning", "info", "note", "success", "hint", "emphasis",
+        "code", "path", "link", "muted",
+    ];
+
+    /// Returns the built-in semantic theme for terminals with a light
+    /// background.
+    ///
+    /// Every style in `Theme::SEMANTIC` is defined. Colors are `Rgb`, so
+    /// they don't depend on the terminal's palette, and each of them has a
+    /// WCAG contrast ratio of at least 4.5 (level AA) on any background
+    /// between white and `#e8e8e8`.
+    pub fn light() -> Theme {
+        LIGHT.parse().expect("valid built-in theme")
+    }
+
+    /// Returns the built-in semantic theme for terminals with a dark
+    /// background.
+    ///
+    /// Every style in `Theme::SEMANTIC` is defined. Colors are `Rgb`, so
+    /// they don't depend on the terminal's palette, and each of them has a
+    /// WCAG contrast ratio of at least 4.5 (level AA) on any background
+    /// between black and `#303030`.
+    pub fn dark() -> Theme {
+        DARK.parse().expect("valid built-in theme")
+    }
+
+    /// Returns the built-in semantic theme that doesn't use any colors.
+    ///
+    /// Every style in `Theme::SEMANTIC` is defined using only bold,
+    /// underlined and italic text, so it works on any background and on
+    /// terminals without color support. Since there are fewer combinations
+    /// of those than styles, some styles look alike, and `info` and `muted`
+    /// are left plain.
+    pub fn monochrome() -> Theme {
+        MONOCHROME.parse().expect("valid built-in theme")
+    }
+
+    /// Load the definitions in the given text into this theme.
+    ///
+    /// Definitions may inherit from styles that are defined later in the
+    /// text or that are already in this theme, and replace existing styles
+    /// with the same name. This makes it possible to load user settings on
+    /// top of an application's defaults.
+    ///
+    /// If the text contains an error, then the theme is left unchanged.
+    pub fn load(&mut self, text: &str) -> Result<&mut Theme, ThemeError> {
+        let mut defs = BTreeMap::new();
+        for (i, line) in text.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let syntax = || ThemeError::Syntax {
+                line: i + 1,
+                text: trimmed.to_string(),
+            };
+            let eq = line.find('=').ok_or_else(syntax)?;
+            let name = line[..eq].trim();
+            if !is_name(name) {
+                return Err(syntax());
+            }
+            // A name that's also a setting, e.g., `bold` or `red`, couldn't
+            // be inherited from, since the setting would take precedence.
+            if name.parse::<ColorSpec>().is_ok() {
+                return Err(ThemeError::ReservedName {
+                    line: i + 1,
+                    name: name.to_string(),
+                });
+            }
+            let def = Definition { line: i + 1, value: &line[eq + 1..], eq };
+            if defs.insert(name, def).is_some() {
+                return Err(ThemeError::Duplicate {
+                    line: i + 1,
+                    name: name.to_string(),
+                });
+            }
+        }
+
+        let mut resolver = Resolver {
+            theme: self,
+            defs: &defs,
+            resolved: BTreeMap::new(),
+            path: vec![],
+        };
+        for name in defs.keys() {
+            resolver.resolve(name)?;
+        }
+        let resolved = resolver.resolved;
+        for (name, spec) in resolved {
+            self.styles.insert(name.to_string(), spec);
+        }
+        Ok(self)
+    }
+}
+
+impl FromStr for Theme {
+    type Err = ThemeError;
+
+    fn from_str(text: &str) -> Result<Theme, ThemeError> {
+        let mut theme = Theme::new();
+        theme.load(text)?;
+        Ok(theme)
+    }
+}
+
+/// The built-in semantic theme for light backgrounds.
+const LIGHT: &str = "\
+error = fg:#c01c28,bold
+warning = fg:#9a4f00,bold
+info = fg:#1a5fb4
+note = fg:#00707a
+success = fg:#26712b
+hint = fg:#7a3e9d,italic
+emphasis = bold
+code = fg:#a0306b
+path = fg:#0a3069
+link = fg:#1a5fb4,underline
+muted = fg:#5e5e5e
+";
+
+/// The built-in semantic theme for dark backgrounds.
+const DARK: &str = "\
+error = fg:#ff7b72,bold
+warning = fg:#f0b442,bold
+info = fg:#6cb6ff
+note = fg:#56d4dd
+success = fg:#7ee787
+hint = fg:#d2a8ff,italic
+emphasis = bold
+code = fg:#ffa657
+path = fg:#a5d6ff
+link = fg:#6cb6ff,underline
+muted = fg:#9a9a9a
+";
+
+/// The built-in semantic theme without colors.
+const MONOCHROME: &str = "\
+error = bold,underline
+warning = bold
+info =
+note = italic
+success = bold
+hint = italic
+emphasis = bold
+code = bold,italic
+path = italic,underline
+link = underline
+muted =
+";
+
+/// A definition of a style that hasn't been resolved yet.
+struct Definition<'a> {
+    /// The line number of the definition.
+    line: usize,
+    /// Everything after the `=`.
+    value: &'a str,
+    /// The byte offset of the `=` in the line.
+    eq: usize,
+}
+
+/// Resolves the inheritance of the definitions in a theme's text.
+struct Resolver<'t, 'a> {
+    theme: &'t Theme,
+    defs: &'t BTreeMap<&'a str, Definition<'a>>,
+    resolved: BTreeMap<&'a str, ColorSpec>,
+    /// The names of the definitions that are being resolved, each of which
+    /// inherits from the next one.
+    path: Vec<&'a str>,
+}
+
+impl<'a> Resolver<'_, 'a> {
+    fn resolve(&mut self, name: &'a str) -> Result<ColorSpec, ThemeError> {
+        if let Some(&spec) = self.resolved.get(name) {
+            return Ok(spec);
+        }
+        let Some(def) = self.defs.get(name) else {
+            // Only names that are defined somewhere are resolved.
+            return Ok(self.theme.styles[name]);
+        };
+        if let Some(start) = self.path.iter().position(|&n| n == name) {
+            let mut names: Vec<String> =
+                self.path[start..].iter().map(|n| n.to_string()).collect();
+            names.push(name.to_string());
+            return Err(ThemeError::Cycle { line: def.line, names });
+        }
+
+        self.path.push(name);
+        let mut spec = ColorSpec::new();
+        for (offset, item) in split_spec(def.value) {
+            let item_spec = if item.is_empty() {
+                continue;
+            } else if self.defs.contains_key(item)
+                || self.theme.styles.contains_key(item)
+            {
+                self.resolve(item)?
+            } else {
+                match ColorSpec::parse_strict(item) {
+                    Ok(item_spec) => item_spec,
+                    // An unknown word is reported as a misspelled
+                    // attribute or color only if it doesn't resemble a
+                    // style.
+                    Err(err)
+                        if is_name(item)
+                            && (self.suggest(item).is_some()
+                                || matches!(
+                                    err,
+                                    ColorSpecParseError::UnknownAttribute {
+                                        suggestion: None,
+                                        ..
+                                    }
+                                )) =>
+                    {
+                        return Err(ThemeError::UnknownReference {
+                            line: def.line,
+                            name: name.to_string(),
+                            reference: item.to_string(),
+                            suggestion: self.suggest(item),
+                        });
+                    }
+                    Err(mut err) => {
+                        err.shift_offset(def.eq + 1 + offset);
+                        return Err(ThemeError::InvalidSpec {
+                            line: def.line,
+                            name: name.to_string(),
+                            err,
+                        });
+                    }
+                }
+            };
+            spec.apply(&item_spec);
+            if !item_spec.reset() {
+                spec.set_reset(false);
+            }
+        }
+        self.path.pop();
+        self.resolved.insert(name, spec);
+        Ok(spec)
+    }
+}
+
+impl Resolver<'_, '_> {
+    /// Returns the defined name that most closely resembles an unknown
+    /// one, if any.
+    fn suggest(&self, unknown: &str) -> Option<String> {
+        let names = self.defs.keys().map(|n| n.to_string());
+        closest(unknown, names.chain(self.theme.styles.keys().cloned()))
+    }
+}
+
+/// Returns true if `name` is a valid name for a style.
+fn is_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"-_.".contains(&b))
+}
+
+/// An error from loading a `Theme` from text.
+///
+/// Each error carries the line number of the definition that caused it,
+/// starting at 1.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ThemeError {
+    /// A line that isn't a comment or a definition of the form
+    /// `name = style`, or whose name isn't valid.
+    Syntax {
+        /// The line number.
+        line: usize,
+        /// The line, without leading and trailing whitespace.
+        text: String,
+    },
+    /// A style whose name is also a setting, such as an attribute (`bold`,
+    /// `nobold`), `intense`, `reset` or a color (`red`, `123`).
+    ReservedName {
+        /// The line number.
+        line: usize,
+        /// The name of the style.
+        name: String,
+    },
+    /// A style that's defined more than once.
+    Duplicate {
+        /// The line number of the second definition.
+        line: usize,
+        /// The name of the style.
+        name: String,
+    },
+    /// A style that inherits from a style that isn't defined.
+    UnknownReference {
+        /// The line number.
+        line: usize,
+        /// The name of the style.
+        name: String,
+        /// The name of the style that isn't defined.
+        reference: String,
+        /// A similar name that's defined, if there is one.
+        suggestion: Option<String>,
+    },
+    /// Styles that inherit from each other in a cycle.
+    Cycle {
+        /// The line number of the definition where the cycle was found.
+        line: usize,
+        /// The names of the styles in the cycle, each of which inherits
+        /// from the next one. The first and last names are the same.
+        names: Vec<String>,
+    },
+    /// A style with an invalid setting.
+    InvalidSpec {
+        /// The line number.
+        line: usize,
+        /// The name of the style.
+        name: String,
+        /// The error from parsing the setting. Its offset is relative to
+        /// the start of the line.
+        err: ColorSpecParseError,
+    },
+}
+
+impl ThemeError {
+    /// Returns the line number of the definition that caused this error.
+    pub fn line(&self) -> usize {
+        use self::ThemeError::*;
+        match *self {
+            Syntax { line, .. }
+            | ReservedName { line, .. }
+            | Duplicate { line, .. }
+            | UnknownReference { line, .. }
+            | Cycle { line, .. }
+            | InvalidSpec { line, .. } => line,
+        }
+    }
+}
+
+impl std::error::Error for ThemeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ThemeError::InvalidSpec { err, .. } => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use self::ThemeError::*;
+        write!(f, "line {}: ", self.line())?;
+        match self {
+            Syntax { text, .. } => {
+                write!(f, "expected 'name = style', but found '{text}'")
+            }
+            ReservedName { name, .. } => write!(
+                f,
+                "'{name}' can't be the name of a style, since it's a setting"
+            ),
+            Duplicate { name, .. } => {
+                write!(f, "style '{name}' is defined more than once")
+            }
+            UnknownReference { name, reference, suggestion, .. } => {
+                write!(
+                    f,
+                    "style '{name}' inherits from unknown style \
+                     '{reference}'"
+                )?;
+                match suggestion {
+                    Some(suggestion) => {
+                        write!(f, ", did you mean '{suggestion}'?")
+                    }
+                    None => Ok(()),
+                }
+            }
+            Cycle { names, .. } => write!(
+                f,
+                "styles inherit from each other in a cycle: {}",
+                names.join(" -> ")
+            ),
+            InvalidSpec { name, err, .. } => {
+                write!(f, "invalid style '{name}': {err}")
+            }
+        }
+    }
+}
+
+/// A writer with a `Theme`, whose styles can be set by name with
+/// `WriteColor::set_style`.
+///
+/// All other methods are passed through to the inner writer.
+#[derive(Clone, Debug)]
+pub struct Themed<W> {
+    wtr: W,
+    theme: Theme,
+}
+
+impl<W> Themed<W> {
+    /// Create a new writer that sets styles from the given theme.
+    pub fn new(wtr: W, theme: Theme) -> Themed<W> {
+        Themed { wtr, theme }
+    }
+
+    /// Replace the theme of this writer.
+    pub fn set_theme(&mut self, theme: Theme) -> &mut Themed<W> {
+        self.theme = theme;
+        self
+    }
+
+    /// Return a mutable reference to the theme of this writer.
+    pub fn theme_mut(&mut self) -> &mut Theme {
+        &mut self.theme
+    }
+
+    /// Consume this `Themed` value and return the inner writer.
+    pub fn into_inner(self) -> W {
+        self.wtr
+    }
+
+    /// Return a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        &self.wtr
+    }
+
+    /// Return a mutable reference to the inner writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.wtr
+    }
+}
+
+impl<W: io::Write> io::Write for Themed<W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.wtr.write(buf)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.wtr.flush()
+    }
+}
+
+impl<W: WriteColor> WriteColor for Themed<W> {
+    #[inline]
+    fn supports_color(&self) -> bool {
+        self.wtr.supports_color()
+    }
+
+    #[inline]
+    fn supports_hyperlinks(&self) -> bool {
+        self.wtr.supports_hyperlinks()
+    }
+
+    #[inline]
+    fn color_level(&self) -> ColorLevel {
+        self.wtr.color_level()
+    }
+
+    #[inline]
+    fn set_color(&mut self, spec: &ColorSpec) -> io::Result<()> {
+        self.wtr.set_color(spec)
+    }
+
+    #[inline]
+    fn set_hyperlink(&mut self, link: &HyperlinkSpec) -> io::Result<()> {
+        self.wtr.set_hyperlink(link)
+    }
+
+    #[inline]
+    fn reset(&mut self) -> io::Result<()> {
+        self.wtr.reset()
+    }
+
+    #[inline]
+    fn is_synchronous(&self) -> bool {
+        self.wtr.is_synchronous()
+    }
+
+    #[inline]
+    fn theme(&self) -> Option<&Theme> {
+        Some(&self.theme)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Ansi, Attribute, Color};
+
+    fn load_err(text: &str) -> ThemeError {
+        text.parse::<Theme>().unwrap_err()
+    }
+
+    #[test]
+    fn inheritance() {
+        let theme: Theme = "\
+            warning = base,fg:yellow\n\
+            base = bold,fg:red\n\
+            quiet = warning,nobold\n"
+            .parse()
+            .unwrap();
+        let warning = theme.get("warning").unwrap();
+        assert!(warning.bold());
+        assert_eq!(warning.fg(), Some(&Color::Yellow));
+        let quiet = theme.get("quiet").unwrap();
+        assert_eq!(quiet.attr(Attribute::Bold), Some(false));
+        assert_eq!(quiet.fg(), Some(&Color::Yellow));
+    }
+
+    #[test]
+    fn cycles() {
+        let names = |err| match err {
+            ThemeError::Cycle { names, .. } => names,
+            err => panic!("expected a cycle, got {err:?}"),
+        };
+        assert_eq!(names(load_err("a = a,bold")), ["a", "a"]);
+        assert_eq!(
+            names(load_err("a = b\nb = c,italic\nc = a\n")),
+            ["a", "b", "c", "a"],
+        );
+        let err = load_err("x = bold\n\nb = a\na = b\n");
+        assert_eq!(err.line(), 4);
+        assert_eq!(names(err), ["a", "b", "a"]);
+    }
+
+    #[test]
+    fn unknown_references() {
+        let err = load_err("header = bold\ntitle = headr,italic\n");
+        assert_eq!(
+            err,
+            ThemeError::UnknownReference {
+                line: 2,
+                name: "title".to_string(),
+                reference: "headr".to_string(),
+                suggestion: Some("header".to_string()),
+            }
+        );
+        let err = load_err("title = missing\n");
+        assert!(matches!(
+            err,
+            ThemeError::UnknownReference { suggestion: None, .. }
+        ));
+        // Misspelled settings that don't resemble a style are reported as
+        // such.
+        let err = load_err("title = bodl\n");
+        assert!(matches!(err, ThemeError::InvalidSpec { .. }), "{err:?}");
+        let err = load_err("title = redd\n");
+        assert!(matches!(err, ThemeError::InvalidSpec { .. }), "{err:?}");
+    }
+
+    #[test]
+    fn references_to_existing_styles() {
+        let mut theme = Theme::dark();
+        theme.load("fatal = error,underline").unwrap();
+        let fatal = theme.get("fatal").unwrap();
+        assert!(fatal.bold() && fatal.underline());
+        assert_eq!(fatal.fg(), theme.get("error").unwrap().fg());
+    }
+
+    #[test]
+    fn reserved_names() {
+        for name in ["bold", "nobold", "intense", "noreset", "red", "123"] {
+            let err = load_err(&format!("{name} = italic"));
+            assert!(
+                matches!(err, ThemeError::ReservedName { line: 1, .. }),
+                "{name}: {err:?}",
+            );
+        }
+        assert!("bold-red = bold,fg:red".parse::<Theme>().is_ok());
+    }
+
+    #[test]
+    fn errors_leave_theme_unchanged() {
+        let mut theme = Theme::light();
+        let before = theme.clone();
+        assert!(theme.load("extra = bold\nerror = nope.nope\n").is_err());
+        assert_eq!(theme, before);
+    }
+
+    #[test]
+    fn set_style() {
+        let mut wtr = Themed::new(Ansi(vec![]), Theme::monochrome());
+        wtr.set_style("emphasis").unwrap();
+        let err = wtr.set_style("emphasys").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(wtr.get_ref().get_ref(), b"\x1B[0m\x1B[1m");
+
+        let mut wtr = Ansi(vec![]);
+        let err = wtr.set_style("emphasis").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(wtr.get_ref().is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{ColorSpec, HyperlinkSpec};
+use crate::{ColorLevel, ColorSpec, HyperlinkSpec, Theme};
 use std::io;
 
 /// This trait describes the behavior of writers that support colored output.
@@ -53,6 +53,22 @@ pub trait WriteColor: io::Write {
         false
     }
 
+    /// Returns the range of colors that this writer can faithfully display.
+    ///
+    /// Applications can use this to pick colors that will render as
+    /// intended, e.g., by choosing between an RGB theme and one built from
+    /// the named colors.
+    ///
+    /// This defaults to `ColorLevel::Ansi16` when `supports_color` returns
+    /// true and `ColorLevel::None` otherwise.
+    fn color_level(&self) -> ColorLevel {
+        if self.supports_color() {
+            ColorLevel::Ansi16
+        } else {
+            ColorLevel::None
+        }
+    }
+
     /// Returns true if and only if the underlying writer must synchronously
     /// interact with an end user's device in order to control colors. By
     /// default, this always returns `false`.
@@ -62,15 +78,47 @@ pub trait WriteColor: io::Write {
     fn is_synchronous(&self) -> bool {
         false
     }
+
+    /// Returns the theme that `set_style` looks up styles in, if this
+    /// writer has one.
+    ///
+    /// This defaults to `None`. Use `Themed` to give a writer a theme.
+    fn theme(&self) -> Option<&Theme> {
+        None
+    }
+
+    /// Set the color settings of the writer to the style with the given
+    /// name in its theme.
+    ///
+    /// If the writer has no theme, or its theme doesn't define the style,
+    /// then an error of kind `io::ErrorKind::InvalidInput` is returned and
+    /// the color settings are left as is.
+    fn set_style(&mut self, name: &str) -> io::Result<()> {
+        let spec = match self.theme() {
+            None => Err(format!("no theme to look up style '{name}' in")),
+            Some(theme) => theme
+                .get(name)
+                .copied()
+                .ok_or_else(|| format!("unknown style '{name}'")),
+        }
+        .map_err(|msg| io::Error::new(io::ErrorKind::InvalidInput, msg))?;
+        self.set_color(&spec)
+    }
 }
 
 impl<T: ?Sized + WriteColor> WriteColor for &mut T {
     fn supports_color(&self) -> bool {
         (**self).supports_color()
     }
+    fn theme(&self) -> Option<&Theme> {
+        (**self).theme()
+    }
     fn supports_hyperlinks(&self) -> bool {
         (**self).supports_hyperlinks()
     }
+    fn color_level(&self) -> ColorLevel {
+        (**self).color_level()
+    }
     fn set_color(&mut self, spec: &ColorSpec) -> io::Result<()> {
         (**self).set_color(spec)
     }
@@ -86,9 +134,15 @@ impl<T: ?Sized + WriteColor> WriteColor for Box<T> {
     fn supports_color(&self) -> bool {
         (**self).supports_color()
     }
+    fn theme(&self) -> Option<&Theme> {
+        (**self).theme()
+    }
     fn supports_hyperlinks(&self) -> bool {
         (**self).supports_hyperlinks()
     }
+    fn color_level(&self) -> ColorLevel {
+        (**self).color_level()
+    }
     fn set_color(&mut self, spec: &ColorSpec) -> io::Result<()> {
         (**self).set_color(spec)
     }
@@ -2,6 +2,8 @@ use std::env;
 use std::fmt;
 use std::str::FromStr;
 
+use crate::quantize::ansi16_color;
+
 /// ColorChoice represents the color preferences of an end user.
 ///
 /// The `Default` implementation for this type will select `Auto`, which tries
@@ -10,6 +12,10 @@ use std::str::FromStr;
 /// The `FromStr` implementation for this type converts a lowercase kebab-case
 /// string of the variant name to the corresponding variant. Any other string
 /// results in an error.
+///
+/// Whatever the choice, the writers in this crate that end up writing colors
+/// also read the `TERMCOLOR_CVD` environment variable to pick a color
+/// transform. See `ColorTransform::from_env`.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum ColorChoice {
     /// Try very hard to emit colors. This includes emitting ANSI colors
@@ -98,6 +104,43 @@ impl ColorChoice {
         true
     }
 
+    /// Returns the color level that a writer built with this choice should
+    /// use in the current environment.
+    pub(crate) fn color_level(&self) -> ColorLevel {
+        if self.should_attempt_color() {
+            ColorLevel::env_depth()
+        } else {
+            ColorLevel::None
+        }
+    }
+
+    /// Returns true if the current terminal is known to support underline
+    /// styles other than a single straight line.
+    pub(crate) fn styled_underlines(&self) -> bool {
+        if env::var_os("KITTY_WINDOW_ID").is_some() {
+            return true;
+        }
+        if let Ok(Ok(v)) = env::var("VTE_VERSION").map(|v| v.parse::<u32>())
+            && v >= 5102
+        {
+            return true;
+        }
+        if let Ok("WezTerm" | "ghostty") = env::var("TERM_PROGRAM").as_deref()
+        {
+            return true;
+        }
+        match env::var("TERM") {
+            Ok(term) => {
+                term == "xterm-kitty"
+                    || term == "wezterm"
+                    || term == "xterm-ghostty"
+                    || term.starts_with("foot")
+                    || term.starts_with("contour")
+            }
+            Err(_) => false,
+        }
+    }
+
     /// Returns true if this choice should forcefully use ANSI color codes.
     ///
     /// It's possible that ANSI is still the correct choice even if this
@@ -119,6 +162,83 @@ impl ColorChoice {
     }
 }
 
+/// ColorLevel describes how many colors a terminal can display.
+///
+/// Levels are ordered from least to most capable, so that, e.g.,
+/// `level >= ColorLevel::Ansi256` checks whether 256 colors are available.
+///
+/// Writers report their level via `WriteColor::color_level`. For the writers
+/// in this crate that write to a standard stream, the level is detected from
+/// the environment when the writer is created. See `ColorLevel::from_env`
+/// for details.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ColorLevel {
+    /// No colors at all.
+    None,
+    /// The 8 base colors and their bright variants.
+    Ansi16,
+    /// The 256 color palette.
+    Ansi256,
+    /// 24-bit RGB colors.
+    TrueColor,
+}
+
+impl ColorLevel {
+    /// Detects the color level of the current terminal from the
+    /// environment.
+    ///
+    /// This returns `None` in the same circumstances that
+    /// `ColorChoice::Auto` would disable colors, e.g., when `TERM=dumb` or
+    /// `NO_COLOR` is set. Otherwise, the following signals are consulted, in
+    /// order:
+    ///
+    /// * `COLORTERM=truecolor` or `COLORTERM=24bit` selects `TrueColor`.
+    /// * `WT_SESSION` being set (i.e., Windows Terminal) selects `TrueColor`.
+    /// * A `TERM_PROGRAM` of a terminal known to support 24-bit colors, such
+    ///   as `iTerm.app` or `WezTerm`, selects `TrueColor`, while
+    ///   `Apple_Terminal` selects `Ansi256`.
+    /// * A `TERM` ending in `-direct` selects `TrueColor`, and a `TERM`
+    ///   containing `256color` selects `Ansi256`.
+    ///
+    /// If none of these apply, then `Ansi16` is returned. On Windows, an
+    /// unset `TERM` selects `TrueColor` instead, since consoles that support
+    /// ANSI escape sequences also support 24-bit colors.
+    ///
+    /// This detection procedure may change over time.
+    pub fn from_env() -> ColorLevel {
+        ColorChoice::Auto.color_level()
+    }
+
+    /// Returns the color depth advertised by the environment, assuming that
+    /// colors are enabled.
+    fn env_depth() -> ColorLevel {
+        if let Some(ct) = env::var_os("COLORTERM")
+            && (ct == "truecolor" || ct == "24bit")
+        {
+            return ColorLevel::TrueColor;
+        }
+        if env::var_os("WT_SESSION").is_some() {
+            return ColorLevel::TrueColor;
+        }
+        match env::var("TERM_PROGRAM").as_deref() {
+            Ok("iTerm.app" | "WezTerm" | "vscode" | "ghostty" | "Hyper") => {
+                return ColorLevel::TrueColor;
+            }
+            Ok("Apple_Terminal") => return ColorLevel::Ansi256,
+            _ => {}
+        }
+        match env::var("TERM") {
+            Ok(term) if term.ends_with("-direct") => ColorLevel::TrueColor,
+            Ok(term) if term.contains("256color") => ColorLevel::Ansi256,
+            Ok(_) => ColorLevel::Ansi16,
+            #[cfg(windows)]
+            Err(_) => ColorLevel::TrueColor,
+            #[cfg(not(windows))]
+            Err(_) => ColorLevel::Ansi16,
+        }
+    }
+}
+
 /// An error that occurs when parsing a `ColorChoice` fails.
 #[derive(Clone, Debug)]
 pub struct ColorChoiceParseError {
@@ -146,17 +266,30 @@ impl fmt::Display for ColorChoiceParseError {
 }
 
 /// A color specification.
-#[derive(Clone, Debug, Eq, PartialEq)]
+///
+/// Besides colors, a specification holds a number of text attributes, such
+/// as bold or italic. Each `Attribute` is either unset, turned on or turned
+/// off. Attributes that are turned off are written with their SGR "off"
+/// code, e.g., `\x1B[22m` for bold, so that a style can be removed without
+/// resetting every other setting. Unset attributes aren't written at all.
+///
+/// The boolean getters, such as `ColorSpec::bold`, return true only for
+/// attributes that are turned on. The boolean setters, such as
+/// `ColorSpec::set_bold`, turn an attribute on or unset it. Use
+/// `ColorSpec::attr` and `ColorSpec::set_attr` to access all three states.
+///
+/// A specification is small and `Copy`, since the attributes are stored as
+/// bit flags, and it implements `Hash` and `Ord`, so that it can be used as
+/// a key in maps and sets. The order has no meaning beyond being consistent.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct ColorSpec {
     pub(crate) fg_color: Option<Color>,
     pub(crate) bg_color: Option<Color>,
-    pub(crate) bold: bool,
+    pub(crate) underline_color: Option<Color>,
+    pub(crate) underline_style: UnderlineStyle,
     pub(crate) intense: bool,
-    pub(crate) underline: bool,
-    pub(crate) dimmed: bool,
-    pub(crate) italic: bool,
     pub(crate) reset: bool,
-    pub(crate) strikethrough: bool,
+    pub(crate) attrs: Attrs,
 }
 
 impl Default for ColorSpec {
@@ -164,13 +297,11 @@ impl Default for ColorSpec {
         ColorSpec {
             fg_color: None,
             bg_color: None,
-            bold: false,
+            underline_color: None,
+            underline_style: UnderlineStyle::Single,
             intense: false,
-            underline: false,
-            dimmed: false,
-            italic: false,
             reset: true,
-            strikethrough: false,
+            attrs: Attrs::default(),
         }
     }
 }
@@ -203,59 +334,204 @@ impl ColorSpec {
         self
     }
 
+    /// Get the underline color.
+    pub fn underline_color(&self) -> Option<&Color> {
+        self.underline_color.as_ref()
+    }
+
+    /// Set the underline color.
+    ///
+    /// This is written with the SGR codes 58 and 59, which are supported by
+    /// the same terminals as the styles of `UnderlineStyle`. Setting it to
+    /// `Color::Default` resets the underline to the foreground color. This
+    /// doesn't enable the underline by itself.
+    pub fn set_underline_color(
+        &mut self,
+        color: Option<Color>,
+    ) -> &mut ColorSpec {
+        self.underline_color = color;
+        self
+    }
+
+    /// Get the state of the given attribute: `None` if it's unset, and
+    /// otherwise whether it's turned on or off.
+    pub fn attr(&self, attr: Attribute) -> Option<bool> {
+        self.attrs.get(attr)
+    }
+
+    /// Set the state of the given attribute.
+    ///
+    /// `Some(true)` turns the attribute on and `Some(false)` turns it off.
+    /// `None` unsets it, so that it's left as is when this specification is
+    /// applied (unless reset is enabled).
+    ///
+    /// Turning the underline on keeps its current style, if it's already
+    /// on, and otherwise uses `UnderlineStyle::Single`.
+    pub fn set_attr(
+        &mut self,
+        attr: Attribute,
+        state: Option<bool>,
+    ) -> &mut ColorSpec {
+        if attr == Attribute::Underline && state != Some(true) {
+            self.underline_style = UnderlineStyle::Single;
+        }
+        self.attrs.set(attr, state);
+        self
+    }
+
     /// Get whether this is bold or not.
     pub fn bold(&self) -> bool {
-        self.bold
+        self.attr(Attribute::Bold) == Some(true)
     }
 
     /// Set whether the text is bolded or not.
     pub fn set_bold(&mut self, yes: bool) -> &mut ColorSpec {
-        self.bold = yes;
-        self
+        self.set_attr(Attribute::Bold, yes.then_some(true))
     }
 
     /// Get whether this is dimmed or not.
     pub fn dimmed(&self) -> bool {
-        self.dimmed
+        self.attr(Attribute::Dimmed) == Some(true)
     }
 
     /// Set whether the text is dimmed or not.
     pub fn set_dimmed(&mut self, yes: bool) -> &mut ColorSpec {
-        self.dimmed = yes;
-        self
+        self.set_attr(Attribute::Dimmed, yes.then_some(true))
     }
 
     /// Get whether this is italic or not.
     pub fn italic(&self) -> bool {
-        self.italic
+        self.attr(Attribute::Italic) == Some(true)
     }
 
     /// Set whether the text is italicized or not.
     pub fn set_italic(&mut self, yes: bool) -> &mut ColorSpec {
-        self.italic = yes;
-        self
+        self.set_attr(Attribute::Italic, yes.then_some(true))
     }
 
     /// Get whether this is underline or not.
     pub fn underline(&self) -> bool {
-        self.underline
+        self.attr(Attribute::Underline) == Some(true)
     }
 
     /// Set whether the text is underlined or not.
+    ///
+    /// Enabling the underline keeps its current style, if one is set, and
+    /// otherwise uses `UnderlineStyle::Single`.
     pub fn set_underline(&mut self, yes: bool) -> &mut ColorSpec {
-        self.underline = yes;
-        self
+        self.set_attr(Attribute::Underline, yes.then_some(true))
+    }
+
+    /// Get the style of the underline, if the text is underlined.
+    pub fn underline_style(&self) -> Option<UnderlineStyle> {
+        self.underline().then_some(self.underline_style)
+    }
+
+    /// Set the style of the underline.
+    ///
+    /// `None` disables the underline, like `set_underline(false)`.
+    pub fn set_underline_style(
+        &mut self,
+        style: Option<UnderlineStyle>,
+    ) -> &mut ColorSpec {
+        match style {
+            None => self.set_attr(Attribute::Underline, None),
+            Some(style) => {
+                self.attrs.set(Attribute::Underline, Some(true));
+                self.underline_style = style;
+                self
+            }
+        }
     }
 
     /// Get whether this is strikethrough or not.
     pub fn strikethrough(&self) -> bool {
-        self.strikethrough
+        self.attr(Attribute::Strikethrough) == Some(true)
     }
 
     /// Set whether the text is strikethrough or not.
     pub fn set_strikethrough(&mut self, yes: bool) -> &mut ColorSpec {
-        self.strikethrough = yes;
-        self
+        self.set_attr(Attribute::Strikethrough, yes.then_some(true))
+    }
+
+    /// Get whether this is blinking or not.
+    pub fn blink(&self) -> bool {
+        self.attr(Attribute::Blink) == Some(true)
+    }
+
+    /// Set whether the text blinks slowly or not.
+    pub fn set_blink(&mut self, yes: bool) -> &mut ColorSpec {
+        self.set_attr(Attribute::Blink, yes.then_some(true))
+    }
+
+    /// Get whether this is rapidly blinking or not.
+    pub fn rapid_blink(&self) -> bool {
+        self.attr(Attribute::RapidBlink) == Some(true)
+    }
+
+    /// Set whether the text blinks rapidly or not.
+    ///
+    /// Many terminals don't support this, and blink slowly instead.
+    pub fn set_rapid_blink(&mut self, yes: bool) -> &mut ColorSpec {
+        self.set_attr(Attribute::RapidBlink, yes.then_some(true))
+    }
+
+    /// Get whether this is reversed or not.
+    pub fn reverse(&self) -> bool {
+        self.attr(Attribute::Reverse) == Some(true)
+    }
+
+    /// Set whether the foreground and background colors of the text are
+    /// swapped or not. This is also known as reverse video.
+    pub fn set_reverse(&mut self, yes: bool) -> &mut ColorSpec {
+        self.set_attr(Attribute::Reverse, yes.then_some(true))
+    }
+
+    /// Get whether this is hidden or not.
+    pub fn hidden(&self) -> bool {
+        self.attr(Attribute::Hidden) == Some(true)
+    }
+
+    /// Set whether the text is hidden or not.
+    ///
+    /// Hidden (or concealed) text is still written, but the terminal
+    /// doesn't display it. It can usually still be selected and copied.
+    pub fn set_hidden(&mut self, yes: bool) -> &mut ColorSpec {
+        self.set_attr(Attribute::Hidden, yes.then_some(true))
+    }
+
+    /// Get whether this is framed or not.
+    pub fn framed(&self) -> bool {
+        self.attr(Attribute::Framed) == Some(true)
+    }
+
+    /// Set whether the text is framed or not.
+    ///
+    /// Few terminals support this.
+    pub fn set_framed(&mut self, yes: bool) -> &mut ColorSpec {
+        self.set_attr(Attribute::Framed, yes.then_some(true))
+    }
+
+    /// Get whether this is encircled or not.
+    pub fn encircled(&self) -> bool {
+        self.attr(Attribute::Encircled) == Some(true)
+    }
+
+    /// Set whether the text is encircled or not.
+    ///
+    /// Few terminals support this.
+    pub fn set_encircled(&mut self, yes: bool) -> &mut ColorSpec {
+        self.set_attr(Attribute::Encircled, yes.then_some(true))
+    }
+
+    /// Get whether this is overlined or not.
+    pub fn overline(&self) -> bool {
+        self.attr(Attribute::Overline) == Some(true)
+    }
+
+    /// Set whether the text is overlined or not.
+    pub fn set_overline(&mut self, yes: bool) -> &mut ColorSpec {
+        self.set_attr(Attribute::Overline, yes.then_some(true))
     }
 
     /// Get whether reset is enabled or not.
@@ -307,27 +583,324 @@ impl ColorSpec {
     }
 
     /// Returns true if this color specification has no colors or styles.
+    ///
+    /// Attributes that are turned off count as styles.
     pub fn is_none(&self) -> bool {
         self.fg_color.is_none()
             && self.bg_color.is_none()
-            && !self.bold
-            && !self.underline
-            && !self.dimmed
-            && !self.italic
+            && self.underline_color.is_none()
             && !self.intense
-            && !self.strikethrough
+            && self.attrs.is_empty()
     }
 
     /// Clears this color specification so that it has no color/style settings.
     pub fn clear(&mut self) {
         self.fg_color = None;
         self.bg_color = None;
-        self.bold = false;
-        self.underline = false;
+        self.underline_color = None;
         self.intense = false;
-        self.dimmed = false;
-        self.italic = false;
-        self.strikethrough = false;
+        self.underline_style = UnderlineStyle::Single;
+        self.attrs = Attrs::default();
+    }
+
+    /// Returns this color specification in the syntax accepted by its
+    /// `FromStr` impl, e.g., `fg:red,bg:#ff8800,bold,!italic`.
+    ///
+    /// Parsing the returned string always yields a specification equal to
+    /// this one. Colors are written using `Color`'s `Display` impl,
+    /// attributes that are turned off are prefixed with `!`, and `noreset`
+    /// is included when reset is disabled. A specification with no colors
+    /// or styles (and reset enabled) yields an empty string.
+    ///
+    /// Note that this differs from `ColorSpec`'s `Display` impl, which
+    /// writes ANSI escape sequences.
+    pub fn to_spec_string(&self) -> String {
+        let mut parts = vec![];
+        if let Some(c) = self.fg() {
+            parts.push(format!("fg:{c}"));
+        }
+        if let Some(c) = self.bg() {
+            parts.push(format!("bg:{c}"));
+        }
+        if let Some(c) = self.underline_color() {
+            parts.push(format!("ul:{c}"));
+        }
+        for attr in Attribute::ALL {
+            match self.attr(attr) {
+                None => {}
+                Some(false) => parts.push(format!("!{}", attr.name())),
+                Some(true) => match self.underline_style {
+                    style
+                        if attr == Attribute::Underline
+                            && style != UnderlineStyle::Single =>
+                    {
+                        parts.push(format!("underline:{}", style.name()))
+                    }
+                    _ => parts.push(attr.name().to_string()),
+                },
+            }
+        }
+        if self.intense {
+            parts.push("intense".to_string());
+        }
+        if !self.reset {
+            parts.push("noreset".to_string());
+        }
+        parts.join(",")
+    }
+}
+
+/// A text attribute of a `ColorSpec`.
+///
+/// Each attribute is either unset, turned on or turned off. See
+/// `ColorSpec::attr` and `ColorSpec::set_attr`.
+///
+/// Some attributes share the SGR code that turns them off, so turning one
+/// of them off also turns the other off in the terminal.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Attribute {
+    /// Bold text, or increased intensity. Written as SGR 1 and turned off
+    /// with SGR 22, which also turns off `Dimmed`.
+    Bold,
+    /// Dimmed text, or decreased intensity. Written as SGR 2 and turned off
+    /// with SGR 22, which also turns off `Bold`.
+    Dimmed,
+    /// Italic text. Written as SGR 3 and turned off with SGR 23.
+    Italic,
+    /// Underlined text. Written as SGR 4 (or one of the styles of
+    /// `UnderlineStyle`) and turned off with SGR 24.
+    Underline,
+    /// Crossed out text. Written as SGR 9 and turned off with SGR 29.
+    Strikethrough,
+    /// Slowly blinking text. Written as SGR 5 and turned off with SGR 25,
+    /// which also turns off `RapidBlink`.
+    Blink,
+    /// Rapidly blinking text. Written as SGR 6 and turned off with SGR 25,
+    /// which also turns off `Blink`.
+    RapidBlink,
+    /// Swapped foreground and background colors. Written as SGR 7 and
+    /// turned off with SGR 27.
+    Reverse,
+    /// Hidden text. Written as SGR 8 and turned off with SGR 28.
+    Hidden,
+    /// Framed text. Written as SGR 51 and turned off with SGR 54, which
+    /// also turns off `Encircled`.
+    Framed,
+    /// Encircled text. Written as SGR 52 and turned off with SGR 54, which
+    /// also turns off `Framed`.
+    Encircled,
+    /// Overlined text. Written as SGR 53 and turned off with SGR 55.
+    Overline,
+}
+
+impl Attribute {
+    /// Every attribute, in the order in which they're written.
+    pub const ALL: [Attribute; 12] = [
+        Attribute::Bold,
+        Attribute::Dimmed,
+        Attribute::Italic,
+        Attribute::Underline,
+        Attribute::Strikethrough,
+        Attribute::Blink,
+        Attribute::RapidBlink,
+        Attribute::Reverse,
+        Attribute::Hidden,
+        Attribute::Framed,
+        Attribute::Encircled,
+        Attribute::Overline,
+    ];
+
+    /// Returns the name of this attribute in a color specification string.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Attribute::Bold => "bold",
+            Attribute::Dimmed => "dimmed",
+            Attribute::Italic => "italic",
+            Attribute::Underline => "underline",
+            Attribute::Strikethrough => "strikethrough",
+            Attribute::Blink => "blink",
+            Attribute::RapidBlink => "rapid-blink",
+            Attribute::Reverse => "reverse",
+            Attribute::Hidden => "hidden",
+            Attribute::Framed => "framed",
+            Attribute::Encircled => "encircled",
+            Attribute::Overline => "overline",
+        }
+    }
+
+    /// Returns the attribute with the given name, if any.
+    pub(crate) fn from_name(name: &str) -> Option<Attribute> {
+        match name {
+            "bold" => Some(Attribute::Bold),
+            "dimmed" => Some(Attribute::Dimmed),
+            "italic" => Some(Attribute::Italic),
+            "underline" => Some(Attribute::Underline),
+            "strikethrough" => Some(Attribute::Strikethrough),
+            "blink" => Some(Attribute::Blink),
+            "rapid-blink" => Some(Attribute::RapidBlink),
+            "reverse" => Some(Attribute::Reverse),
+            "hidden" | "conceal" => Some(Attribute::Hidden),
+            "framed" => Some(Attribute::Framed),
+            "encircled" => Some(Attribute::Encircled),
+            "overline" => Some(Attribute::Overline),
+            _ => None,
+        }
+    }
+
+    /// Returns the SGR code that turns this attribute on.
+    pub(crate) fn on_sgr(self) -> &'static str {
+        match self {
+            Attribute::Bold => "1",
+            Attribute::Dimmed => "2",
+            Attribute::Italic => "3",
+            Attribute::Underline => "4",
+            Attribute::Strikethrough => "9",
+            Attribute::Blink => "5",
+            Attribute::RapidBlink => "6",
+            Attribute::Reverse => "7",
+            Attribute::Hidden => "8",
+            Attribute::Framed => "51",
+            Attribute::Encircled => "52",
+            Attribute::Overline => "53",
+        }
+    }
+
+    /// Returns the bit of this attribute in `Attrs`.
+    fn bit(self) -> u16 {
+        1 << self as u16
+    }
+
+    /// Returns the SGR code that turns this attribute off.
+    pub(crate) fn off_sgr(self) -> &'static str {
+        match self {
+            Attribute::Bold => "22",
+            Attribute::Dimmed => "22",
+            Attribute::Italic => "23",
+            Attribute::Underline => "24",
+            Attribute::Strikethrough => "29",
+            Attribute::Blink => "25",
+            Attribute::RapidBlink => "25",
+            Attribute::Reverse => "27",
+            Attribute::Hidden => "28",
+            Attribute::Framed => "54",
+            Attribute::Encircled => "54",
+            Attribute::Overline => "55",
+        }
+    }
+}
+
+/// The states of all attributes of a `ColorSpec`, as bit flags.
+#[derive(Clone, Copy, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub(crate) struct Attrs {
+    /// The attributes that are set, i.e., turned on or off.
+    set: u16,
+    /// The attributes that are turned on, which is a subset of `set`.
+    on: u16,
+}
+
+impl Attrs {
+    /// Returns the state of the given attribute.
+    pub(crate) fn get(self, attr: Attribute) -> Option<bool> {
+        let bit = attr.bit();
+        (self.set & bit != 0).then_some(self.on & bit != 0)
+    }
+
+    /// Set the state of the given attribute.
+    pub(crate) fn set(&mut self, attr: Attribute, state: Option<bool>) {
+        let bit = attr.bit();
+        self.set &= !bit;
+        self.on &= !bit;
+        if let Some(on) = state {
+            self.set |= bit;
+            if on {
+                self.on |= bit;
+            }
+        }
+    }
+
+    /// Returns true if no attribute is set.
+    pub(crate) fn is_empty(self) -> bool {
+        self.set == 0
+    }
+}
+
+impl fmt::Debug for Attrs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut set = f.debug_set();
+        for attr in Attribute::ALL {
+            match self.get(attr) {
+                None => {}
+                Some(true) => {
+                    set.entry(&attr);
+                }
+                Some(false) => {
+                    set.entry(&format_args!("!{attr:?}"));
+                }
+            }
+        }
+        set.finish()
+    }
+}
+
+/// The style of an underline.
+///
+/// Styles other than `Single` are an extension to the standard SGR codes,
+/// written as `4:2` through `4:5`. They're supported by terminals such as
+/// kitty, WezTerm, foot and those based on VTE. Writers that don't know
+/// whether the terminal supports them write a plain underline instead. See
+/// `AnsiWriter::set_styled_underlines`.
+///
+/// The `Default` implementation returns `Single`.
+#[derive(
+    Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd,
+)]
+pub enum UnderlineStyle {
+    /// A single straight line.
+    #[default]
+    Single,
+    /// Two straight lines.
+    Double,
+    /// A wavy line, as commonly used to mark errors.
+    Curly,
+    /// A dotted line.
+    Dotted,
+    /// A dashed line.
+    Dashed,
+}
+
+impl UnderlineStyle {
+    /// Returns the name of this style in a color specification string.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            UnderlineStyle::Single => "single",
+            UnderlineStyle::Double => "double",
+            UnderlineStyle::Curly => "curly",
+            UnderlineStyle::Dotted => "dotted",
+            UnderlineStyle::Dashed => "dashed",
+        }
+    }
+
+    /// Returns the style with the given name, if any.
+    pub(crate) fn from_name(name: &str) -> Option<UnderlineStyle> {
+        match &*name.to_lowercase() {
+            "single" => Some(UnderlineStyle::Single),
+            "double" => Some(UnderlineStyle::Double),
+            "curly" => Some(UnderlineStyle::Curly),
+            "dotted" => Some(UnderlineStyle::Dotted),
+            "dashed" => Some(UnderlineStyle::Dashed),
+            _ => None,
+        }
+    }
+
+    /// Returns the parameters of the SGR sequence that enables this style.
+    pub(crate) fn sgr(self) -> &'static str {
+        match self {
+            UnderlineStyle::Single => "4",
+            UnderlineStyle::Double => "4:2",
+            UnderlineStyle::Curly => "4:3",
+            UnderlineStyle::Dotted => "4:4",
+            UnderlineStyle::Dashed => "4:5",
+        }
     }
 }
 
@@ -336,20 +909,42 @@ impl ColorSpec {
 /// The `Ansi256` and `Rgb` colors will only output the correct codes when
 /// paired with the `Ansi` `WriteColor` implementation.
 ///
+/// The `Bright*` colors are the "aixterm" high intensity colors, which are
+/// written with the SGR codes 90-97 (foreground) and 100-107 (background).
+/// Unlike `ColorSpec::set_intense`, they never imply bold text or an index
+/// into the 256 color palette.
+///
 /// This set may expand over time.
 ///
 /// This type has a `FromStr` impl that can parse colors from their human
 /// readable form. The format is as follows:
 ///
 /// 1. Any of the explicitly listed colors in English. They are matched
-///    case insensitively.
+///    case insensitively. The bright variants are written with a `bright-`
+///    prefix, e.g., `bright-red`, and `Default` is written as `default`.
 /// 2. A single 8-bit integer, in either decimal or hexadecimal format.
 /// 3. A triple of 8-bit integers separated by a comma, where each integer is
 ///    in decimal or hexadecimal format.
+/// 4. A hex color in the CSS `#rgb` or `#rrggbb` notation.
+/// 5. The CSS functional notation `rgb(r, g, b)`, where each component is
+///    either an integer in the range `[0, 255]` or a percentage. Components
+///    may be separated by commas or whitespace.
+/// 6. The functional notations `hsl(h, s, l)`, `hsv(h, s, v)` and
+///    `oklch(l c h)`. Hues are in degrees, with an optional `deg` suffix.
+///    Saturation, lightness and value are percentages (the `%` is optional).
+///    The OKLCH lightness is either a number in `[0, 1]` or a percentage,
+///    and its chroma is either a number or a percentage where `100%` is
+///    `0.4`. These are converted with `Color::from_hsl`, `Color::from_hsv`
+///    and `Color::from_oklch`.
+/// 7. Any CSS color name or common X11 color name, such as `orange` or
+///    `navajowhite`. These are matched case insensitively and always produce
+///    an `Rgb` color. X11's numbered variants (e.g., `red3` or `gray50`) are
+///    not recognized. Note that the eight English names from (1) take
+///    precedence.
 ///
 /// Hexadecimal numbers are written with a `0x` prefix.
 #[allow(missing_docs)]
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 #[non_exhaustive]
 pub enum Color {
     Black,
@@ -360,11 +955,85 @@ pub enum Color {
     Magenta,
     Yellow,
     White,
+    BrightBlack,
+    BrightBlue,
+    BrightGreen,
+    BrightRed,
+    BrightCyan,
+    BrightMagenta,
+    BrightYellow,
+    BrightWhite,
     Ansi256(u8),
     Rgb(u8, u8, u8),
+    /// The terminal's default color, which is written with the SGR codes 39
+    /// (foreground) and 49 (background). Unlike a full reset, this leaves
+    /// the other color and all styles untouched.
+    Default,
 }
 
 impl Color {
+    /// Parses a CSS hex color string, i.e., `#rgb` or `#rrggbb`.
+    fn from_str_hex(s: &str) -> Result<Color, ParseColorError> {
+        let err = || ParseColorError::new(ParseColorErrorKind::InvalidHex, s);
+        let digits = s.strip_prefix('#').ok_or_else(err)?;
+        if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(err());
+        }
+        let channel = |i: usize, len: usize| {
+            u8::from_str_radix(&digits[i * len..(i + 1) * len], 16)
+                .map_err(|_| err())
+        };
+        match digits.len() {
+            3 => {
+                let (r, g, b) =
+                    (channel(0, 1)?, channel(1, 1)?, channel(2, 1)?);
+                Ok(Color::Rgb(r * 17, g * 17, b * 17))
+            }
+            6 => {
+                Ok(Color::Rgb(channel(0, 2)?, channel(1, 2)?, channel(2, 2)?))
+            }
+            _ => Err(err()),
+        }
+    }
+
+    /// Parses a color written in CSS functional notation, e.g.,
+    /// `rgb(255, 136, 0)`.
+    fn from_str_function(s: &str) -> Result<Color, ParseColorError> {
+        let err =
+            || ParseColorError::new(ParseColorErrorKind::InvalidFunction, s);
+        let (name, args) = split_function(s).ok_or_else(err)?;
+        let &[a1, a2, a3] = args.as_slice() else {
+            return Err(err());
+        };
+        match &*name.to_lowercase() {
+            "rgb" => {
+                let r = parse_rgb_component(a1).ok_or_else(err)?;
+                let g = parse_rgb_component(a2).ok_or_else(err)?;
+                let b = parse_rgb_component(a3).ok_or_else(err)?;
+                Ok(Color::Rgb(r, g, b))
+            }
+            "hsl" => {
+                let h = parse_hue(a1).ok_or_else(err)?;
+                let s = parse_percentage(a2).ok_or_else(err)?;
+                let l = parse_percentage(a3).ok_or_else(err)?;
+                Ok(Color::from_hsl(h, s, l))
+            }
+            "hsv" => {
+                let h = parse_hue(a1).ok_or_else(err)?;
+                let s = parse_percentage(a2).ok_or_else(err)?;
+                let v = parse_percentage(a3).ok_or_else(err)?;
+                Ok(Color::from_hsv(h, s, v))
+            }
+            "oklch" => {
+                let l = parse_fraction(a1, 1.0).ok_or_else(err)?;
+                let c = parse_fraction(a2, 0.4).ok_or_else(err)?;
+                let h = parse_hue(a3).ok_or_else(err)?;
+                Ok(Color::from_oklch(l, c, h))
+            }
+            _ => Err(err()),
+        }
+    }
+
     /// Parses a numeric color string, either ANSI or RGB.
     fn from_str_numeric(s: &str) -> Result<Color, ParseColorError> {
         // The "ansi256" format is a single number (decimal or hex)
@@ -386,47 +1055,98 @@ impl Color {
             if let Some(n) = parse_number(codes[0]) {
                 Ok(Color::Ansi256(n))
             } else if s.chars().all(|c| c.is_ascii_hexdigit()) {
-                Err(ParseColorError {
-                    kind: ParseColorErrorKind::InvalidAnsi256,
-                    given: s.to_string(),
-                })
+                Err(ParseColorError::new(
+                    ParseColorErrorKind::InvalidAnsi256,
+                    s,
+                ))
             } else {
-                Err(ParseColorError {
-                    kind: ParseColorErrorKind::InvalidName,
-                    given: s.to_string(),
-                })
+                Err(ParseColorError::new(ParseColorErrorKind::InvalidName, s))
             }
         } else if codes.len() == 3 {
             let mut v = vec![];
             for code in codes {
-                let n = parse_number(code).ok_or_else(|| ParseColorError {
-                    kind: ParseColorErrorKind::InvalidRgb,
-                    given: s.to_string(),
+                let n = parse_number(code).ok_or_else(|| {
+                    ParseColorError::new(ParseColorErrorKind::InvalidRgb, s)
                 })?;
                 v.push(n);
             }
             Ok(Color::Rgb(v[0], v[1], v[2]))
         } else {
             Err(if s.contains(",") {
-                ParseColorError {
-                    kind: ParseColorErrorKind::InvalidRgb,
-                    given: s.to_string(),
-                }
+                ParseColorError::new(ParseColorErrorKind::InvalidRgb, s)
             } else {
-                ParseColorError {
-                    kind: ParseColorErrorKind::InvalidName,
-                    given: s.to_string(),
-                }
+                ParseColorError::new(ParseColorErrorKind::InvalidName, s)
             })
         }
     }
 }
 
+/// Splits a string of the form `name(arg, arg, ...)` into its name and
+/// arguments. Arguments may be separated by commas, whitespace or a `/`.
+///
+/// Returns `None` if the string isn't of that form.
+fn split_function(s: &str) -> Option<(&str, Vec<&str>)> {
+    let s = s.trim();
+    let open = s.find('(')?;
+    let body = s[open + 1..].strip_suffix(')')?;
+    let name = s[..open].trim();
+    if name.is_empty() || body.contains(['(', ')']) {
+        return None;
+    }
+    let args = body
+        .split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+        .filter(|a| !a.is_empty())
+        .collect();
+    Some((name, args))
+}
+
+/// Parses a single `rgb()` component, either an integer in `[0, 255]` or a
+/// percentage in `[0%, 100%]`.
+fn parse_rgb_component(s: &str) -> Option<u8> {
+    if let Some(pct) = s.strip_suffix('%') {
+        let pct: f64 = pct.parse().ok()?;
+        if !(0.0..=100.0).contains(&pct) {
+            return None;
+        }
+        Some((pct * 255.0 / 100.0).round() as u8)
+    } else {
+        s.parse::<u8>().ok()
+    }
+}
+
+/// Parses a hue in degrees, with an optional `deg` suffix.
+fn parse_hue(s: &str) -> Option<f64> {
+    let h: f64 = s.strip_suffix("deg").unwrap_or(s).parse().ok()?;
+    if h.is_finite() { Some(h) } else { None }
+}
+
+/// Parses a percentage in `[0, 100]`, with an optional `%` suffix, into a
+/// fraction in `[0, 1]`.
+fn parse_percentage(s: &str) -> Option<f64> {
+    let pct: f64 = s.strip_suffix('%').unwrap_or(s).parse().ok()?;
+    if (0.0..=100.0).contains(&pct) { Some(pct / 100.0) } else { None }
+}
+
+/// Parses either a plain non-negative number or a percentage, where `100%`
+/// corresponds to `full`.
+fn parse_fraction(s: &str, full: f64) -> Option<f64> {
+    let v = if let Some(pct) = s.strip_suffix('%') {
+        pct.parse::<f64>().ok()? / 100.0 * full
+    } else {
+        s.parse::<f64>().ok()?
+    };
+    if v.is_finite() && v >= 0.0 { Some(v) } else { None }
+}
+
 /// An error from parsing an invalid color specification.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct ParseColorError {
     kind: ParseColorErrorKind,
     given: String,
+    suggestion: Option<String>,
+    /// The byte offset and text of the setting of a `ColorSpec` that the
+    /// color was part of, if any.
+    setting: Option<(usize, String)>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -434,13 +1154,37 @@ enum ParseColorErrorKind {
     InvalidName,
     InvalidAnsi256,
     InvalidRgb,
+    InvalidHex,
+    InvalidFunction,
 }
 
 impl ParseColorError {
+    fn new(kind: ParseColorErrorKind, given: &str) -> ParseColorError {
+        ParseColorError {
+            kind,
+            given: given.to_string(),
+            suggestion: None,
+            setting: None,
+        }
+    }
+
     /// Return the string that couldn't be parsed as a valid color.
     pub fn invalid(&self) -> &str {
         &self.given
     }
+
+    /// Returns the color name that was probably meant, if the string looks
+    /// like a misspelled color name, e.g., `blue` for `bleu`.
+    pub fn suggestion(&self) -> Option<&str> {
+        self.suggestion.as_deref()
+    }
+
+    /// Records the setting of a color specification that this color was
+    /// part of, along with its byte offset.
+    fn in_setting(mut self, offset: usize, setting: &str) -> ParseColorError {
+        self.setting = Some((offset, setting.to_string()));
+        self
+    }
 }
 
 impl std::error::Error for ParseColorError {
@@ -450,6 +1194,8 @@ impl std::error::Error for ParseColorError {
             InvalidName => "unrecognized color name",
             InvalidAnsi256 => "invalid ansi256 color number",
             InvalidRgb => "invalid RGB color triple",
+            InvalidHex => "invalid hex color",
+            InvalidFunction => "invalid color function",
         }
     }
 }
@@ -458,11 +1204,18 @@ impl fmt::Display for ParseColorError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use self::ParseColorErrorKind::*;
         match self.kind {
+            InvalidName if self.suggestion.is_some() => write!(
+                f,
+                "unrecognized color name '{}', did you mean '{}'?",
+                self.given,
+                self.suggestion.as_deref().unwrap_or_default()
+            ),
             InvalidName => write!(
                 f,
                 "unrecognized color name '{}'. Choose from: \
                  black, blue, green, red, cyan, magenta, yellow, \
-                 white",
+                 white, any of those prefixed with 'bright-', default, \
+                 or a CSS or common X11 color name",
                 self.given
             ),
             InvalidAnsi256 => write!(
@@ -478,10 +1231,55 @@ impl fmt::Display for ParseColorError {
                  triple), but is '{}'",
                 self.given
             ),
+            InvalidHex => write!(
+                f,
+                "unrecognized hex color, \
+                 should be '#rgb' or '#rrggbb', but is '{}'",
+                self.given
+            ),
+            InvalidFunction => write!(
+                f,
+                "unrecognized color function, \
+                 should be one of 'rgb(r, g, b)', 'hsl(h, s%, l%)', \
+                 'hsv(h, s%, v%)' or 'oklch(l c h)', but is '{}'",
+                self.given
+            ),
         }
     }
 }
 
+/// Writes a color in a form that its `FromStr` impl parses back into the
+/// same color.
+///
+/// Named colors are written by name (e.g., `bright-red`), `Ansi256` colors
+/// as a decimal number and `Rgb` colors in the `#rrggbb` notation.
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match *self {
+            Color::Black => "black",
+            Color::Blue => "blue",
+            Color::Green => "green",
+            Color::Red => "red",
+            Color::Cyan => "cyan",
+            Color::Magenta => "magenta",
+            Color::Yellow => "yellow",
+            Color::White => "white",
+            Color::BrightBlack => "bright-black",
+            Color::BrightBlue => "bright-blue",
+            Color::BrightGreen => "bright-green",
+            Color::BrightRed => "bright-red",
+            Color::BrightCyan => "bright-cyan",
+            Color::BrightMagenta => "bright-magenta",
+            Color::BrightYellow => "bright-yellow",
+            Color::BrightWhite => "bright-white",
+            Color::Default => "default",
+            Color::Ansi256(n) => return write!(f, "{n}"),
+            Color::Rgb(r, g, b) => return write!(f, "#{r:02x}{g:02x}{b:02x}"),
+        };
+        f.write_str(name)
+    }
+}
+
 impl FromStr for Color {
     type Err = ParseColorError;
 
@@ -495,30 +1293,213 @@ impl FromStr for Color {
             "magenta" => Ok(Color::Magenta),
             "yellow" => Ok(Color::Yellow),
             "white" => Ok(Color::White),
-            _ => Color::from_str_numeric(s),
+            "bright-black" => Ok(Color::BrightBlack),
+            "bright-blue" => Ok(Color::BrightBlue),
+            "bright-green" => Ok(Color::BrightGreen),
+            "bright-red" => Ok(Color::BrightRed),
+            "bright-cyan" => Ok(Color::BrightCyan),
+            "bright-magenta" => Ok(Color::BrightMagenta),
+            "bright-yellow" => Ok(Color::BrightYellow),
+            "bright-white" => Ok(Color::BrightWhite),
+            "default" => Ok(Color::Default),
+            lower => {
+                if s.starts_with('#') {
+                    Color::from_str_hex(s)
+                } else if s.contains('(') {
+                    Color::from_str_function(s)
+                } else if let Some((r, g, b)) = crate::names::lookup(lower) {
+                    Ok(Color::Rgb(r, g, b))
+                } else {
+                    Color::from_str_numeric(s).map_err(|mut err| {
+                        if err.kind == ParseColorErrorKind::InvalidName {
+                            err.suggestion = suggest_color(lower);
+                        }
+                        err
+                    })
+                }
+            }
         }
     }
 }
 
 /// An error from parsing an invalid color specification.
+///
+/// Errors that concern a single setting of a specification, such as `bold`
+/// or `fg:red`, carry the byte offset at which that setting starts along
+/// with the setting itself.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum ColorSpecParseError {
     /// An error parsing a color.
+    ///
+    /// If the color is part of a setting, such as `fg:bleu`, then `offset`
+    /// and `token` return the position and text of that setting. Misspelled
+    /// color names come with a suggestion, see
+    /// `ParseColorError::suggestion`.
     InvalidColor(ParseColorError),
+    /// An unrecognized underline style, e.g., in `underline:wavy`.
+    InvalidUnderlineStyle {
+        /// The byte offset of the setting.
+        offset: usize,
+        /// The setting, e.g., `underline:wavy`.
+        token: String,
+    },
+    /// An unrecognized attribute or key, e.g., `bodl` or `fgg:red`.
+    UnknownAttribute {
+        /// The byte offset of the setting.
+        offset: usize,
+        /// The unrecognized setting.
+        token: String,
+        /// A similar setting that was probably meant, if there is one.
+        suggestion: Option<String>,
+    },
+    /// A setting that was given more than once, e.g., the second `bold`
+    /// in `bold,italic,bold`. This is only reported by
+    /// `ColorSpec::parse_strict`.
+    DuplicateKey {
+        /// The byte offset of the repeated setting.
+        offset: usize,
+        /// The repeated setting.
+        token: String,
+    },
+    /// A setting that contradicts an earlier one, e.g., `!bold` in
+    /// `bold,!bold` or `fg:blue` in `fg:red,fg:blue`. This is only
+    /// reported by `ColorSpec::parse_strict`.
+    ConflictingKeys {
+        /// The byte offset of the later setting.
+        offset: usize,
+        /// The later setting.
+        token: String,
+        /// The earlier setting that it contradicts.
+        previous: String,
+    },
+    /// A color that isn't prefixed with `fg:`. This is only reported by
+    /// `ColorSpec::parse_strict`.
+    BareColor {
+        /// The byte offset of the color.
+        offset: usize,
+        /// The color.
+        token: String,
+    },
+    /// An empty specification, or an empty setting such as the one after
+    /// the trailing comma in `bold,`. This is only reported by
+    /// `ColorSpec::parse_strict`.
+    Empty {
+        /// The byte offset at which a setting was expected.
+        offset: usize,
+    },
+}
+
+impl ColorSpecParseError {
+    /// Returns the byte offset of the setting that caused this error, if
+    /// it's known.
+    pub fn offset(&self) -> Option<usize> {
+        use self::ColorSpecParseError::*;
+        match *self {
+            InvalidColor(ref e) => {
+                e.setting.as_ref().map(|&(offset, _)| offset)
+            }
+            InvalidUnderlineStyle { offset, .. }
+            | UnknownAttribute { offset, .. }
+            | DuplicateKey { offset, .. }
+            | ConflictingKeys { offset, .. }
+            | BareColor { offset, .. }
+            | Empty { offset } => Some(offset),
+        }
+    }
+
+    /// Returns the setting that caused this error, if it's known.
+    pub fn token(&self) -> Option<&str> {
+        use self::ColorSpecParseError::*;
+        match self {
+            InvalidColor(e) => match e.setting {
+                Some((_, ref token)) => Some(token),
+                None => Some(e.invalid()),
+            },
+            InvalidUnderlineStyle { token, .. }
+            | UnknownAttribute { token, .. }
+            | DuplicateKey { token, .. }
+            | ConflictingKeys { token, .. }
+            | BareColor { token, .. } => Some(token),
+            Empty { .. } => None,
+        }
+    }
+
+    /// Adds `by` to the offset of this error, for errors in a setting that
+    /// was parsed on its own.
+    pub(crate) fn shift_offset(&mut self, by: usize) {
+        use self::ColorSpecParseError::*;
+        match self {
+            InvalidColor(e) => {
+                if let Some((offset, _)) = e.setting.as_mut() {
+                    *offset += by;
+                }
+            }
+            InvalidUnderlineStyle { offset, .. }
+            | UnknownAttribute { offset, .. }
+            | DuplicateKey { offset, .. }
+            | ConflictingKeys { offset, .. }
+            | BareColor { offset, .. }
+            | Empty { offset } => *offset += by,
+        }
+    }
 }
 
 impl std::error::Error for ColorSpecParseError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             ColorSpecParseError::InvalidColor(e) => Some(e),
+            _ => None,
         }
     }
 }
 
 impl fmt::Display for ColorSpecParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use self::ColorSpecParseError::*;
         match self {
-            ColorSpecParseError::InvalidColor(e) => write!(f, "{e}"),
+            InvalidColor(e) => match e.setting {
+                Some((offset, ref token)) => {
+                    write!(
+                        f,
+                        "invalid color in '{token}' at byte {offset}: {e}"
+                    )
+                }
+                None => write!(f, "{e}"),
+            },
+            InvalidUnderlineStyle { offset, token } => write!(
+                f,
+                "unrecognized underline style in '{token}' at byte \
+                 {offset}. Choose from: single, double, curly, dotted, \
+                 dashed"
+            ),
+            UnknownAttribute { offset, token, suggestion } => {
+                write!(
+                    f,
+                    "unrecognized attribute '{token}' at byte {offset}"
+                )?;
+                match suggestion {
+                    Some(suggestion) => {
+                        write!(f, ", did you mean '{suggestion}'?")
+                    }
+                    None => Ok(()),
+                }
+            }
+            DuplicateKey { offset, token } => {
+                write!(f, "duplicate setting '{token}' at byte {offset}")
+            }
+            ConflictingKeys { offset, token, previous } => write!(
+                f,
+                "setting '{token}' at byte {offset} conflicts with \
+                 '{previous}'"
+            ),
+            BareColor { offset, token } => write!(
+                f,
+                "color '{token}' at byte {offset} must be written as \
+                 'fg:{token}'"
+            ),
+            Empty { offset } => {
+                write!(f, "expected a setting at byte {offset}")
+            }
         }
     }
 }
@@ -527,45 +1508,313 @@ impl FromStr for ColorSpec {
     type Err = ColorSpecParseError;
 
     fn from_str(spec: &str) -> Result<ColorSpec, ColorSpecParseError> {
-        let mut color_spec = ColorSpec::new();
-        for part in spec.split(',') {
-            let part = part.trim();
-            if part.is_empty() {
-                continue;
+        parse_spec(spec, false)
+    }
+}
+
+impl ColorSpec {
+    /// Parse a color specification, rejecting anything that's ambiguous.
+    ///
+    /// This accepts the same syntax as `ColorSpec`'s `FromStr` impl, which
+    /// is also what `ColorSpec::to_spec_string` writes, except that:
+    ///
+    /// * Colors must be prefixed with `fg:`, `bg:` or `ul:`. A bare color
+    ///   such as `red` is an error, since it's easily confused with a
+    ///   misspelled attribute.
+    /// * Each setting may be given only once. Repeating a setting or
+    ///   contradicting an earlier one, e.g., `bold,!bold`, is an error.
+    /// * Empty specifications and empty settings, e.g., `bold,,italic`, are
+    ///   errors.
+    ///
+    /// Errors carry the byte offset of the offending setting, and
+    /// unrecognized attributes and color names come with a suggestion when
+    /// there's a similar one.
+    pub fn parse_strict(spec: &str) -> Result<ColorSpec, ColorSpecParseError> {
+        parse_spec(spec, true)
+    }
+}
+
+/// A single setting of a color specification.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Setting {
+    Fg(Color),
+    Bg(Color),
+    Ul(Color),
+    Attr(Attribute, bool),
+    Underline(UnderlineStyle),
+    Intense,
+    Reset(bool),
+}
+
+/// The part of a color specification that a setting changes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum SettingKey {
+    Fg,
+    Bg,
+    Ul,
+    Attr(Attribute),
+    Intense,
+    Reset,
+}
+
+impl Setting {
+    /// Parses a setting that's identified by its name or by its key, or
+    /// returns `None` for a bare word that's either a color or unknown.
+    fn parse(
+        offset: usize,
+        part: &str,
+    ) -> Option<Result<Setting, ColorSpecParseError>> {
+        let color = |s: &str| {
+            Color::from_str(s).map_err(|e| {
+                ColorSpecParseError::InvalidColor(e.in_setting(offset, part))
+            })
+        };
+        if let Some(color_str) = part.strip_prefix("fg:") {
+            Some(color(color_str).map(Setting::Fg))
+        } else if let Some(color_str) = part.strip_prefix("bg:") {
+            Some(color(color_str).map(Setting::Bg))
+        } else if let Some(color_str) = part.strip_prefix("ul:") {
+            Some(color(color_str).map(Setting::Ul))
+        } else if let Some(name) = part.strip_prefix("underline:") {
+            Some(
+                UnderlineStyle::from_name(name.trim())
+                    .map(Setting::Underline)
+                    .ok_or_else(|| {
+                        ColorSpecParseError::InvalidUnderlineStyle {
+                            offset,
+                            token: part.to_string(),
+                        }
+                    }),
+            )
+        } else if let Some(attr) = Attribute::from_name(part) {
+            Some(Ok(match attr {
+                Attribute::Underline => {
+                    Setting::Underline(UnderlineStyle::Single)
+                }
+                attr => Setting::Attr(attr, true),
+            }))
+        } else if let Some(attr) = part
+            .strip_prefix('!')
+            .or_else(|| part.strip_prefix("no"))
+            .and_then(Attribute::from_name)
+        {
+            Some(Ok(Setting::Attr(attr, false)))
+        } else if part == "intense" {
+            Some(Ok(Setting::Intense))
+        } else if part == "reset" {
+            Some(Ok(Setting::Reset(true)))
+        } else if part == "noreset" {
+            Some(Ok(Setting::Reset(false)))
+        } else if part.contains(':') {
+            Some(Err(ColorSpecParseError::UnknownAttribute {
+                offset,
+                token: part.to_string(),
+                suggestion: suggest(part),
+            }))
+        } else {
+            None
+        }
+    }
+
+    fn key(self) -> SettingKey {
+        match self {
+            Setting::Fg(_) => SettingKey::Fg,
+            Setting::Bg(_) => SettingKey::Bg,
+            Setting::Ul(_) => SettingKey::Ul,
+            Setting::Attr(attr, _) => SettingKey::Attr(attr),
+            Setting::Underline(_) => SettingKey::Attr(Attribute::Underline),
+            Setting::Intense => SettingKey::Intense,
+            Setting::Reset(_) => SettingKey::Reset,
+        }
+    }
+
+    fn apply(self, spec: &mut ColorSpec) {
+        match self {
+            Setting::Fg(c) => spec.set_fg(Some(c)),
+            Setting::Bg(c) => spec.set_bg(Some(c)),
+            Setting::Ul(c) => spec.set_underline_color(Some(c)),
+            Setting::Attr(attr, yes) => spec.set_attr(attr, Some(yes)),
+            Setting::Underline(style) => spec.set_underline_style(Some(style)),
+            Setting::Intense => spec.set_intense(true),
+            Setting::Reset(yes) => spec.set_reset(yes),
+        };
+    }
+}
+
+/// Parses a color specification. In strict mode, bare colors, repeated
+/// settings and empty settings are rejected.
+///
+/// Otherwise, later settings override earlier ones and empty settings are
+/// ignored. A bare word is parsed as a foreground color.
+///
+/// In both modes, a bare word that isn't a valid setting is reported as an
+/// invalid color if it resembles a color (e.g., `redd` or `#12`) and not an
+/// attribute. Otherwise, it's reported as an unknown attribute.
+fn parse_spec(
+    spec: &str,
+    strict: bool,
+) -> Result<ColorSpec, ColorSpecParseError> {
+    let mut color_spec = ColorSpec::new();
+    let mut seen: Vec<(Setting, &str)> = vec![];
+    for (offset, part) in split_spec(spec) {
+        if part.is_empty() {
+            if strict {
+                return Err(ColorSpecParseError::Empty { offset });
             }
+            continue;
+        }
 
-            if let Some(color_str) = part.strip_prefix("fg:") {
-                let color = Color::from_str(color_str)
-                    .map_err(ColorSpecParseError::InvalidColor)?;
-                color_spec.set_fg(Some(color));
-            } else if let Some(color_str) = part.strip_prefix("bg:") {
-                let color = Color::from_str(color_str)
-                    .map_err(ColorSpecParseError::InvalidColor)?;
-                color_spec.set_bg(Some(color));
-            } else if part == "bold" {
-                color_spec.set_bold(true);
-            } else if part == "dimmed" {
-                color_spec.set_dimmed(true);
-            } else if part == "underline" {
-                color_spec.set_underline(true);
-            } else if part == "italic" {
-                color_spec.set_italic(true);
-            } else if part == "intense" {
-                color_spec.set_intense(true);
-            } else if part == "strikethrough" {
-                color_spec.set_strikethrough(true);
-            } else if part == "reset" {
-                color_spec.set_reset(true);
-            } else if part == "noreset" {
-                color_spec.set_reset(false);
-            } else {
-                let color = Color::from_str(part)
-                    .map_err(ColorSpecParseError::InvalidColor)?;
-                color_spec.set_fg(Some(color));
+        let setting = match Setting::parse(offset, part) {
+            Some(setting) => setting?,
+            None => match Color::from_str(part) {
+                Ok(_) if strict => {
+                    return Err(ColorSpecParseError::BareColor {
+                        offset,
+                        token: part.to_string(),
+                    });
+                }
+                Ok(color) => Setting::Fg(color),
+                Err(mut err) => {
+                    let suggestion = suggest(part);
+                    let is_color = err.kind
+                        != ParseColorErrorKind::InvalidName
+                        || err.suggestion.is_some();
+                    if suggestion.is_some() || !is_color {
+                        return Err(ColorSpecParseError::UnknownAttribute {
+                            offset,
+                            token: part.to_string(),
+                            suggestion,
+                        });
+                    }
+                    if strict {
+                        // Bare colors aren't allowed, so suggest the
+                        // prefixed form of a misspelled color name.
+                        err.suggestion =
+                            err.suggestion.map(|name| format!("fg:{name}"));
+                    }
+                    return Err(ColorSpecParseError::InvalidColor(
+                        err.in_setting(offset, part),
+                    ));
+                }
+            },
+        };
+        if strict {
+            let earlier =
+                seen.iter().find(|&&(s, _)| s.key() == setting.key());
+            if let Some(&(previous, previous_token)) = earlier {
+                let token = part.to_string();
+                return Err(if previous == setting {
+                    ColorSpecParseError::DuplicateKey { offset, token }
+                } else {
+                    ColorSpecParseError::ConflictingKeys {
+                        offset,
+                        token,
+                        previous: previous_token.to_string(),
+                    }
+                });
+            }
+            seen.push((setting, part));
+        }
+        setting.apply(&mut color_spec);
+    }
+    Ok(color_spec)
+}
+
+/// Splits a color specification on commas, except for commas that appear
+/// inside of parentheses, such as in `fg:rgb(255, 136, 0)`.
+///
+/// Each setting is trimmed and returned along with its byte offset.
+pub(crate) fn split_spec(spec: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut depth = 0usize;
+    let mut start = 0;
+    spec.split(move |c: char| {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => return true,
+            _ => {}
+        }
+        false
+    })
+    .map(move |part| {
+        let offset = start;
+        start += part.len() + 1;
+        let trimmed = part.trim_start();
+        (offset + part.len() - trimmed.len(), trimmed.trim_end())
+    })
+}
+
+/// Returns the valid setting that most closely resembles an unrecognized
+/// one, if any of them is close enough to be a likely misspelling.
+fn suggest(token: &str) -> Option<String> {
+    if let Some((key, value)) = token.split_once(':') {
+        return closest(
+            key,
+            ["fg", "bg", "ul", "underline"].map(String::from),
+        )
+        .map(|key| format!("{key}:{value}"));
+    }
+    let mut candidates = vec![];
+    for attr in Attribute::ALL {
+        candidates.push(attr.name().to_string());
+        candidates.push(format!("!{}", attr.name()));
+        candidates.push(format!("no{}", attr.name()));
+    }
+    candidates
+        .extend(["conceal", "intense", "reset", "noreset"].map(String::from));
+    closest(token, candidates)
+}
+
+/// Returns the color name that most closely resembles an unrecognized one,
+/// if any of them is close enough to be a likely misspelling.
+fn suggest_color(name: &str) -> Option<String> {
+    let mut candidates: Vec<String> =
+        (0..16).map(|n| ansi16_color(n).to_string()).collect();
+    candidates.push("default".to_string());
+    candidates.extend(crate::names::names().map(String::from));
+    closest(name, candidates)
+}
+
+/// Returns the candidate with the smallest edit distance to `word`, if
+/// it's small enough relative to the length of `word`.
+pub(crate) fn closest(
+    word: &str,
+    candidates: impl IntoIterator<Item = String>,
+) -> Option<String> {
+    let max = (word.chars().count() / 3).clamp(1, 2);
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(word, &candidate), candidate))
+        .filter(|&(distance, _)| distance <= max)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Returns the number of single character insertions, deletions,
+/// substitutions and transpositions of adjacent characters needed to turn
+/// `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
             }
         }
-        Ok(color_spec)
     }
+    d[a.len()][b.len()]
 }
 
 /// A hyperlink specification.
@@ -598,3 +1847,90 @@ impl fmt::Display for ColorSpec {
         write!(f, "{}", String::from_utf8_lossy(&buf))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the offset and token of the error from strictly parsing the
+    /// given specification.
+    fn strict_err(spec: &str) -> (Option<usize>, Option<String>) {
+        let err = ColorSpec::parse_strict(spec).unwrap_err();
+        (err.offset(), err.token().map(str::to_string))
+    }
+
+    #[test]
+    fn strict_error_offsets() {
+        let cases = [
+            ("", Some(0), None),
+            ("bold,,italic", Some(5), None),
+            ("bold,", Some(5), None),
+            ("bold, red", Some(6), Some("red")),
+            ("italic,bodl", Some(7), Some("bodl")),
+            ("bold,italic,bold", Some(12), Some("bold")),
+            ("fg:red, !bold,  bold", Some(16), Some("bold")),
+            ("bold,fg:rgb(1, 2, 300)", Some(5), Some("fg:rgb(1, 2, 300)")),
+            ("bold,  fg:bleu ", Some(7), Some("fg:bleu")),
+            ("italic,underline:wavy", Some(7), Some("underline:wavy")),
+            ("bold,redd", Some(5), Some("redd")),
+        ];
+        for (spec, offset, token) in cases {
+            assert_eq!(
+                strict_err(spec),
+                (offset, token.map(str::to_string)),
+                "{spec:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn strict_error_kinds() {
+        use ColorSpecParseError::*;
+
+        let err = |spec| ColorSpec::parse_strict(spec).unwrap_err();
+        assert!(matches!(err("bold, red"), BareColor { .. }));
+        assert!(matches!(err("bold,italic,bold"), DuplicateKey { .. }));
+        assert!(matches!(
+            err("fg:red, !bold,  bold"),
+            ConflictingKeys { ref previous, .. } if previous == "!bold"
+        ));
+        assert!(matches!(
+            err("italic,bodl"),
+            UnknownAttribute { suggestion: Some(ref s), .. } if s == "bold"
+        ));
+    }
+
+    #[test]
+    fn bare_words_are_classified_alike() {
+        use ColorSpecParseError::*;
+
+        for (word, suggestion) in [
+            ("redd", Some("red")),
+            ("tomatoe", Some("tomato")),
+            ("xyz", None),
+            ("bodl", Some("bold")),
+            ("#12", None),
+        ] {
+            let lenient = word.parse::<ColorSpec>().unwrap_err();
+            let strict = ColorSpec::parse_strict(word).unwrap_err();
+            match (&lenient, &strict) {
+                (InvalidColor(lenient), InvalidColor(strict)) => {
+                    assert_eq!(lenient.suggestion(), suggestion, "{word}");
+                    assert_eq!(
+                        strict.suggestion(),
+                        suggestion.map(|s| format!("fg:{s}")).as_deref(),
+                        "{word}",
+                    );
+                }
+                (
+                    UnknownAttribute { suggestion: lenient, .. },
+                    UnknownAttribute { suggestion: strict, .. },
+                ) => {
+                    assert_eq!(lenient.as_deref(), suggestion, "{word}");
+                    assert_eq!(strict.as_deref(), suggestion, "{word}");
+                }
+                _ => panic!("{word}: {lenient:?} vs {strict:?}"),
+            }
+        }
+    }
+}
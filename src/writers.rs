@@ -1,4 +1,7 @@
-use crate::{Color, ColorChoice, ColorSpec, HyperlinkSpec, WriteColor};
+use crate::{
+    Attribute, Color, ColorChoice, ColorLevel, ColorSpec, ColorTransform,
+    HyperlinkSpec, UnderlineStyle, WriteColor,
+};
 use std::io::{self, Write};
 use std::sync::atomic::{AtomicBool, Ordering};
 
@@ -116,6 +119,9 @@ impl<'a> io::Write for IoStandardStreamLock<'a> {
 #[derive(Debug)]
 pub struct StandardStream {
     wtr: LossyStandardStream<WriterInner<IoStandardStream>>,
+    /// Whether a lock was taken, which may have changed the terminal state
+    /// that's tracked for minimal transitions.
+    locked: AtomicBool,
 }
 
 /// `StandardStreamLock` is a locked reference to a `StandardStream`.
@@ -140,14 +146,14 @@ pub struct BufferedStandardStream {
 #[derive(Debug)]
 enum WriterInner<W> {
     NoColor(NoColor<W>),
-    Ansi(Ansi<W>),
+    Ansi(AnsiWriter<W>),
 }
 
 /// WriterInnerLock is a (limited) generic representation of a writer.
 #[derive(Debug)]
 enum WriterInnerLock<W> {
     NoColor(NoColor<W>),
-    Ansi(Ansi<W>),
+    Ansi(AnsiWriter<W>),
 }
 
 impl StandardStream {
@@ -157,7 +163,10 @@ impl StandardStream {
     /// If coloring is desired, ANSI escape sequences are used.
     pub fn stdout(choice: ColorChoice) -> StandardStream {
         let wtr = WriterInner::create(StandardStreamType::Stdout, choice);
-        StandardStream { wtr: LossyStandardStream::new(wtr) }
+        StandardStream {
+            wtr: LossyStandardStream::new(wtr),
+            locked: AtomicBool::new(false),
+        }
     }
 
     /// Create a new `StandardStream` with the given color preferences that
@@ -166,7 +175,10 @@ impl StandardStream {
     /// If coloring is desired, ANSI escape sequences are used.
     pub fn stderr(choice: ColorChoice) -> StandardStream {
         let wtr = WriterInner::create(StandardStreamType::Stderr, choice);
-        StandardStream { wtr: LossyStandardStream::new(wtr) }
+        StandardStream {
+            wtr: LossyStandardStream::new(wtr),
+            locked: AtomicBool::new(false),
+        }
     }
 
     /// Lock the underlying writer.
@@ -177,8 +189,78 @@ impl StandardStream {
     /// This method is **not reentrant**. It may panic if `lock` is called
     /// while a `StandardStreamLock` is still alive.
     pub fn lock(&self) -> StandardStreamLock<'_> {
+        self.locked.store(true, Ordering::Relaxed);
         StandardStreamLock::from_stream(self)
     }
+
+    /// Forgets the terminal state tracked for minimal transitions if a lock
+    /// may have changed it.
+    fn forget_locked_state(&mut self) {
+        if std::mem::take(self.locked.get_mut())
+            && let Some(mode) = self.wtr.get_mut().ansi_mode_mut()
+        {
+            mode.current = None;
+        }
+    }
+
+    /// Set whether colors are degraded to the detected color level before
+    /// they're written.
+    ///
+    /// See `AnsiWriter::set_degrade_colors` for details. This has no effect if
+    /// this stream doesn't write colors. Locks created afterwards inherit
+    /// this setting.
+    ///
+    /// This is disabled by default.
+    pub fn set_degrade_colors(&mut self, yes: bool) {
+        if let Some(mode) = self.wtr.get_mut().ansi_mode_mut() {
+            mode.degrade = yes;
+        }
+    }
+
+    /// Set a transformation that's applied to every color before it's
+    /// written, e.g., to simulate a color vision deficiency.
+    ///
+    /// See `AnsiWriter::set_color_transform` for details. This has no effect
+    /// if this stream doesn't write colors.
+    ///
+    /// The default is read from the `TERMCOLOR_CVD` environment variable
+    /// (see `ColorTransform::from_env`), and is `None` if it isn't set.
+    pub fn set_color_transform(&mut self, transform: Option<ColorTransform>) {
+        if let Some(mode) = self.wtr.get_mut().ansi_mode_mut() {
+            mode.transform = transform;
+        }
+    }
+
+    /// Set whether underline styles other than `UnderlineStyle::Single` are
+    /// written as such.
+    ///
+    /// See `AnsiWriter::set_styled_underlines` for details. This has no effect
+    /// if this stream doesn't write colors.
+    ///
+    /// By default, this is enabled only if the terminal is known to support
+    /// underline styles.
+    pub fn set_styled_underlines(&mut self, yes: bool) {
+        if let Some(mode) = self.wtr.get_mut().ansi_mode_mut() {
+            mode.styled_underline = yes;
+        }
+    }
+
+    /// Set whether this stream keeps track of the terminal's state and only
+    /// writes the escape codes needed to get from one color specification
+    /// to the next.
+    ///
+    /// See `AnsiWriter::set_minimal_transitions` for details. This has no
+    /// effect if this stream doesn't write colors. Locks created afterwards
+    /// inherit this setting, but each of them starts out with an unknown
+    /// state, since other locks may have changed it.
+    ///
+    /// This is disabled by default.
+    pub fn set_minimal_transitions(&mut self, yes: bool) {
+        if let Some(mode) = self.wtr.get_mut().ansi_mode_mut() {
+            mode.minimal = yes;
+            mode.current = None;
+        }
+    }
 }
 
 impl<'a> StandardStreamLock<'a> {
@@ -188,7 +270,10 @@ impl<'a> StandardStreamLock<'a> {
                 WriterInnerLock::NoColor(NoColor(w.0.lock()))
             }
             WriterInner::Ansi(ref w) => {
-                WriterInnerLock::Ansi(Ansi(w.0.lock()))
+                // Earlier locks may have changed the terminal state without
+                // the stream knowing, so a lock starts from an unknown state.
+                let mode = AnsiMode { current: None, ..w.mode };
+                WriterInnerLock::Ansi(AnsiWriter { wtr: w.wtr.lock(), mode })
             }
         };
         StandardStreamLock { wtr: stream.wtr.wrap(locked) }
@@ -215,6 +300,72 @@ impl BufferedStandardStream {
             WriterInner::create(StandardStreamType::StderrBuffered, choice);
         BufferedStandardStream { wtr: LossyStandardStream::new(wtr) }
     }
+
+    /// Set whether colors are degraded to the detected color level before
+    /// they're written.
+    ///
+    /// See `AnsiWriter::set_degrade_colors` for details. This has no effect if
+    /// this stream doesn't write colors.
+    ///
+    /// This is disabled by default.
+    pub fn set_degrade_colors(&mut self, yes: bool) {
+        if let Some(mode) = self.wtr.get_mut().ansi_mode_mut() {
+            mode.degrade = yes;
+        }
+    }
+
+    /// Set a transformation that's applied to every color before it's
+    /// written, e.g., to simulate a color vision deficiency.
+    ///
+    /// See `AnsiWriter::set_color_transform` for details. This has no effect
+    /// if this stream doesn't write colors.
+    ///
+    /// The default is read from the `TERMCOLOR_CVD` environment variable
+    /// (see `ColorTransform::from_env`), and is `None` if it isn't set.
+    pub fn set_color_transform(&mut self, transform: Option<ColorTransform>) {
+        if let Some(mode) = self.wtr.get_mut().ansi_mode_mut() {
+            mode.transform = transform;
+        }
+    }
+
+    /// Set whether underline styles other than `UnderlineStyle::Single` are
+    /// written as such.
+    ///
+    /// See `AnsiWriter::set_styled_underlines` for details. This has no effect
+    /// if this stream doesn't write colors.
+    ///
+    /// By default, this is enabled only if the terminal is known to support
+    /// underline styles.
+    pub fn set_styled_underlines(&mut self, yes: bool) {
+        if let Some(mode) = self.wtr.get_mut().ansi_mode_mut() {
+            mode.styled_underline = yes;
+        }
+    }
+
+    /// Set whether this stream keeps track of the terminal's state and only
+    /// writes the escape codes needed to get from one color specification
+    /// to the next.
+    ///
+    /// See `AnsiWriter::set_minimal_transitions` for details. This has no
+    /// effect if this stream doesn't write colors.
+    ///
+    /// This is disabled by default.
+    pub fn set_minimal_transitions(&mut self, yes: bool) {
+        if let Some(mode) = self.wtr.get_mut().ansi_mode_mut() {
+            mode.minimal = yes;
+            mode.current = None;
+        }
+    }
+}
+
+impl<W: io::Write> WriterInner<W> {
+    /// Returns the settings of the ANSI writer, if colors are written.
+    fn ansi_mode_mut(&mut self) -> Option<&mut AnsiMode> {
+        match *self {
+            WriterInner::NoColor(_) => None,
+            WriterInner::Ansi(ref mut wtr) => Some(&mut wtr.mode),
+        }
+    }
 }
 
 impl WriterInner<IoStandardStream> {
@@ -226,7 +377,11 @@ impl WriterInner<IoStandardStream> {
         choice: ColorChoice,
     ) -> WriterInner<IoStandardStream> {
         if choice.should_attempt_color() {
-            WriterInner::Ansi(Ansi(IoStandardStream::new(sty)))
+            let mut wtr = AnsiWriter::new(IoStandardStream::new(sty));
+            wtr.set_color_level(choice.color_level());
+            wtr.set_styled_underlines(choice.styled_underlines());
+            wtr.set_color_transform(ColorTransform::from_env());
+            WriterInner::Ansi(wtr)
         } else {
             WriterInner::NoColor(NoColor(IoStandardStream::new(sty)))
         }
@@ -259,7 +414,11 @@ impl WriterInner<IoStandardStream> {
         if choice.should_attempt_color()
             && (enabled_virtual || choice.should_force_ansi())
         {
-            WriterInner::Ansi(Ansi(IoStandardStream::new(sty)))
+            let mut wtr = AnsiWriter::new(IoStandardStream::new(sty));
+            wtr.set_color_level(choice.color_level());
+            wtr.set_styled_underlines(choice.styled_underlines());
+            wtr.set_color_transform(ColorTransform::from_env());
+            WriterInner::Ansi(wtr)
         } else {
             WriterInner::NoColor(NoColor(IoStandardStream::new(sty)))
         }
@@ -289,8 +448,14 @@ impl WriteColor for StandardStream {
         self.wtr.supports_hyperlinks()
     }
 
+    #[inline]
+    fn color_level(&self) -> ColorLevel {
+        self.wtr.color_level()
+    }
+
     #[inline]
     fn set_color(&mut self, spec: &ColorSpec) -> io::Result<()> {
+        self.forget_locked_state();
         self.wtr.set_color(spec)
     }
 
@@ -301,6 +466,7 @@ impl WriteColor for StandardStream {
 
     #[inline]
     fn reset(&mut self) -> io::Result<()> {
+        self.forget_locked_state();
         self.wtr.reset()
     }
 }
@@ -328,6 +494,11 @@ impl<'a> WriteColor for StandardStreamLock<'a> {
         self.wtr.supports_hyperlinks()
     }
 
+    #[inline]
+    fn color_level(&self) -> ColorLevel {
+        self.wtr.color_level()
+    }
+
     #[inline]
     fn set_color(&mut self, spec: &ColorSpec) -> io::Result<()> {
         self.wtr.set_color(spec)
@@ -367,6 +538,11 @@ impl WriteColor for BufferedStandardStream {
         self.wtr.supports_hyperlinks()
     }
 
+    #[inline]
+    fn color_level(&self) -> ColorLevel {
+        self.wtr.color_level()
+    }
+
     #[inline]
     fn set_color(&mut self, spec: &ColorSpec) -> io::Result<()> {
         self.wtr.set_color(spec)
@@ -416,6 +592,13 @@ impl<W: io::Write> WriteColor for WriterInner<W> {
         }
     }
 
+    fn color_level(&self) -> ColorLevel {
+        match *self {
+            WriterInner::NoColor(ref wtr) => wtr.color_level(),
+            WriterInner::Ansi(ref wtr) => wtr.color_level(),
+        }
+    }
+
     fn set_color(&mut self, spec: &ColorSpec) -> io::Result<()> {
         match *self {
             WriterInner::NoColor(ref mut wtr) => wtr.set_color(spec),
@@ -469,6 +652,13 @@ impl<W: io::Write> WriteColor for WriterInnerLock<W> {
         }
     }
 
+    fn color_level(&self) -> ColorLevel {
+        match *self {
+            WriterInnerLock::NoColor(ref wtr) => wtr.color_level(),
+            WriterInnerLock::Ansi(ref wtr) => wtr.color_level(),
+        }
+    }
+
     fn set_color(&mut self, spec: &ColorSpec) -> io::Result<()> {
         match *self {
             WriterInnerLock::NoColor(ref mut wtr) => wtr.set_color(spec),
@@ -505,6 +695,7 @@ pub struct BufferWriter {
     printed: AtomicBool,
     separator: Option<Vec<u8>>,
     use_color: bool,
+    ansi_mode: AnsiMode,
 }
 
 impl BufferWriter {
@@ -518,6 +709,11 @@ impl BufferWriter {
             printed: AtomicBool::new(false),
             separator: None,
             use_color,
+            ansi_mode: AnsiMode {
+                styled_underline: choice.styled_underlines(),
+                transform: ColorTransform::from_env(),
+                ..AnsiMode::with_level(choice.color_level())
+            },
         }
     }
 
@@ -556,11 +752,18 @@ impl BufferWriter {
         };
         let mut stream = LossyStandardStream::new(IoStandardStream::new(sty));
         stream.is_console = is_console;
+        let color_level =
+            if use_color { choice.color_level() } else { ColorLevel::None };
         BufferWriter {
             stream,
             printed: AtomicBool::new(false),
             separator: None,
             use_color,
+            ansi_mode: AnsiMode {
+                styled_underline: choice.styled_underlines(),
+                transform: ColorTransform::from_env(),
+                ..AnsiMode::with_level(color_level)
+            },
         }
     }
 
@@ -584,12 +787,74 @@ impl BufferWriter {
         self.separator = sep;
     }
 
+    /// Set whether buffers created by this writer degrade colors to the
+    /// detected color level before they're written.
+    ///
+    /// See `AnsiWriter::set_degrade_colors` for details. This only applies to
+    /// buffers created after this is called.
+    ///
+    /// This is disabled by default.
+    pub fn set_degrade_colors(&mut self, yes: bool) {
+        self.ansi_mode.degrade = yes;
+    }
+
+    /// Set a transformation that's applied to every color before it's
+    /// written by buffers created by this writer.
+    ///
+    /// See `AnsiWriter::set_color_transform` for details. This only applies
+    /// to buffers created after this is called.
+    ///
+    /// The default is read from the `TERMCOLOR_CVD` environment variable
+    /// (see `ColorTransform::from_env`), and is `None` if it isn't set.
+    pub fn set_color_transform(&mut self, transform: Option<ColorTransform>) {
+        self.ansi_mode.transform = transform;
+    }
+
+    /// Set whether buffers created by this writer write underline styles
+    /// other than `UnderlineStyle::Single` as such.
+    ///
+    /// See `AnsiWriter::set_styled_underlines` for details. This only applies
+    /// to buffers created after this is called.
+    ///
+    /// By default, this is enabled only if the terminal is known to support
+    /// underline styles.
+    pub fn set_styled_underlines(&mut self, yes: bool) {
+        self.ansi_mode.styled_underline = yes;
+    }
+
+    /// Set whether buffers created by this writer keep track of their
+    /// state and only write the escape codes needed to get from one color
+    /// specification to the next.
+    ///
+    /// See `AnsiWriter::set_minimal_transitions` for details. Each buffer
+    /// starts out with an unknown state, since it may be printed at any point.
+    /// This only applies to buffers created after this is called.
+    ///
+    /// This is disabled by default.
+    pub fn set_minimal_transitions(&mut self, yes: bool) {
+        self.ansi_mode.minimal = yes;
+    }
+
     /// Creates a new `Buffer` with the current color preferences.
     ///
     /// A `Buffer` satisfies both `io::Write` and `WriteColor`. A `Buffer` can
     /// be printed using the `print` method.
     pub fn buffer(&self) -> Buffer {
-        if self.use_color { Buffer::ansi() } else { Buffer::no_color() }
+        if self.use_color {
+            Buffer(BufferInner::Ansi(AnsiWriter {
+                wtr: vec![],
+                mode: self.ansi_mode,
+            }))
+        } else {
+            Buffer::no_color()
+        }
+    }
+
+    /// Returns the color level detected for this writer's stream.
+    ///
+    /// Buffers created by this writer report the same level.
+    pub fn color_level(&self) -> ColorLevel {
+        self.ansi_mode.level
     }
 
     /// Prints the contents of the given buffer.
@@ -610,7 +875,7 @@ impl BufferWriter {
         }
         match buf.0 {
             BufferInner::NoColor(ref b) => stream.write_all(&b.0)?,
-            BufferInner::Ansi(ref b) => stream.write_all(&b.0)?,
+            BufferInner::Ansi(ref b) => stream.write_all(&b.wtr)?,
         }
         self.printed.store(true, Ordering::Relaxed);
         Ok(())
@@ -638,7 +903,7 @@ enum BufferInner {
     /// directives.
     NoColor(NoColor<Vec<u8>>),
     /// Apply coloring using ANSI escape sequences embedded into the buffer.
-    Ansi(Ansi<Vec<u8>>),
+    Ansi(AnsiWriter<Vec<u8>>),
 }
 
 impl Buffer {
@@ -671,7 +936,7 @@ impl Buffer {
 
     /// Create a buffer that uses ANSI escape sequences.
     pub fn ansi() -> Buffer {
-        Buffer(BufferInner::Ansi(Ansi(vec![])))
+        Buffer(BufferInner::Ansi(AnsiWriter::new(vec![])))
     }
 
     /// Returns true if and only if this buffer is empty.
@@ -683,7 +948,7 @@ impl Buffer {
     pub fn len(&self) -> usize {
         match self.0 {
             BufferInner::NoColor(ref b) => b.0.len(),
-            BufferInner::Ansi(ref b) => b.0.len(),
+            BufferInner::Ansi(ref b) => b.wtr.len(),
         }
     }
 
@@ -691,7 +956,11 @@ impl Buffer {
     pub fn clear(&mut self) {
         match self.0 {
             BufferInner::NoColor(ref mut b) => b.0.clear(),
-            BufferInner::Ansi(ref mut b) => b.0.clear(),
+            BufferInner::Ansi(ref mut b) => {
+                b.wtr.clear();
+                // The buffer may be printed anywhere now.
+                b.mode.current = None;
+            }
         }
     }
 
@@ -699,7 +968,7 @@ impl Buffer {
     pub fn into_inner(self) -> Vec<u8> {
         match self.0 {
             BufferInner::NoColor(b) => b.0,
-            BufferInner::Ansi(b) => b.0,
+            BufferInner::Ansi(b) => b.wtr,
         }
     }
 
@@ -707,7 +976,7 @@ impl Buffer {
     pub fn as_slice(&self) -> &[u8] {
         match self.0 {
             BufferInner::NoColor(ref b) => &b.0,
-            BufferInner::Ansi(ref b) => &b.0,
+            BufferInner::Ansi(ref b) => &b.wtr,
         }
     }
 
@@ -715,7 +984,7 @@ impl Buffer {
     pub fn as_mut_slice(&mut self) -> &mut [u8] {
         match self.0 {
             BufferInner::NoColor(ref mut b) => &mut b.0,
-            BufferInner::Ansi(ref mut b) => &mut b.0,
+            BufferInner::Ansi(ref mut b) => &mut b.wtr,
         }
     }
 }
@@ -755,6 +1024,14 @@ impl WriteColor for Buffer {
         }
     }
 
+    #[inline]
+    fn color_level(&self) -> ColorLevel {
+        match self.0 {
+            BufferInner::NoColor(ref w) => w.color_level(),
+            BufferInner::Ansi(ref w) => w.color_level(),
+        }
+    }
+
     #[inline]
     fn set_color(&mut self, spec: &ColorSpec) -> io::Result<()> {
         match self.0 {
@@ -847,6 +1124,10 @@ impl<W: io::Write> WriteColor for NoColor<W> {
 }
 
 /// Satisfies `WriteColor` using standard ANSI escape sequences.
+///
+/// Every color specification is written as given. Use `AnsiWriter` to
+/// configure how they're written, e.g., to degrade colors to what a
+/// terminal supports.
 #[derive(Clone, Debug)]
 pub struct Ansi<W>(pub W);
 
@@ -879,6 +1160,197 @@ impl<W: io::Write> io::Write for Ansi<W> {
         self.0.write(buf)
     }
 
+    // See the `write_all` implementation of `AnsiWriter`.
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.0.write_all(buf)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl<W: io::Write> WriteColor for Ansi<W> {
+    #[inline]
+    fn supports_color(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn supports_hyperlinks(&self) -> bool {
+        true
+    }
+
+    /// Returns `ColorLevel::TrueColor`, since every color is written as
+    /// given.
+    #[inline]
+    fn color_level(&self) -> ColorLevel {
+        ColorLevel::TrueColor
+    }
+
+    #[inline]
+    fn set_color(&mut self, spec: &ColorSpec) -> io::Result<()> {
+        AnsiWriter::new(&mut self.0).set_color(spec)
+    }
+
+    #[inline]
+    fn set_hyperlink(&mut self, link: &HyperlinkSpec) -> io::Result<()> {
+        AnsiWriter::new(&mut self.0).set_hyperlink(link)
+    }
+
+    #[inline]
+    fn reset(&mut self) -> io::Result<()> {
+        AnsiWriter::new(&mut self.0).reset()
+    }
+}
+
+/// Satisfies `WriteColor` using standard ANSI escape sequences, with
+/// settings that control how color specifications are written.
+///
+/// With the default settings, this writes the same escape sequences as
+/// `Ansi`. `StandardStream`, `BufferedStandardStream` and `BufferWriter`
+/// write colors with an `AnsiWriter` whose settings are derived from their
+/// `ColorChoice` and the environment.
+#[derive(Clone, Debug)]
+pub struct AnsiWriter<W> {
+    wtr: W,
+    mode: AnsiMode,
+}
+
+/// The settings of an `AnsiWriter`, aside from the writer itself.
+#[derive(Clone, Copy, Debug)]
+struct AnsiMode {
+    level: ColorLevel,
+    degrade: bool,
+    transform: Option<ColorTransform>,
+    styled_underline: bool,
+    minimal: bool,
+    /// The terminal state left by the last escape sequence, if it's known.
+    /// This is only tracked when writing minimal transitions.
+    current: Option<ColorSpec>,
+}
+
+impl Default for AnsiMode {
+    fn default() -> AnsiMode {
+        AnsiMode::with_level(ColorLevel::TrueColor)
+    }
+}
+
+impl AnsiMode {
+    fn with_level(level: ColorLevel) -> AnsiMode {
+        AnsiMode {
+            level,
+            degrade: false,
+            transform: None,
+            styled_underline: true,
+            minimal: false,
+            current: None,
+        }
+    }
+}
+
+impl<W: Write> AnsiWriter<W> {
+    /// Create a new writer with the default settings.
+    ///
+    /// The writer reports a color level of `ColorLevel::TrueColor`, since it
+    /// emits every color as given.
+    pub fn new(wtr: W) -> AnsiWriter<W> {
+        AnsiWriter { wtr, mode: AnsiMode::default() }
+    }
+
+    /// Set the color level reported by this writer's
+    /// `WriteColor::color_level`.
+    ///
+    /// This is useful when the capabilities of the terminal that will
+    /// eventually display this writer's output are known.
+    pub fn set_color_level(&mut self, level: ColorLevel) {
+        self.mode.level = level;
+    }
+
+    /// Set whether colors are degraded to this writer's color level before
+    /// they're written.
+    ///
+    /// When enabled, `Rgb` and `Ansi256` colors that the color level can't
+    /// display are replaced by their closest supported equivalent (see
+    /// `Color::to_level`), and intense colors are written as bright colors
+    /// below `ColorLevel::Ansi256`. At `ColorLevel::None`, colors are
+    /// dropped entirely while other styles are still written.
+    ///
+    /// This is disabled by default.
+    pub fn set_degrade_colors(&mut self, yes: bool) {
+        self.mode.degrade = yes;
+    }
+
+    /// Set a transformation that's applied to every color before it's
+    /// written.
+    ///
+    /// This can be used to check or adapt all colored output for people with
+    /// a color vision deficiency, without changing the colors that an
+    /// application picks. The transformation is applied before colors are
+    /// degraded.
+    ///
+    /// The default is `None`.
+    pub fn set_color_transform(&mut self, transform: Option<ColorTransform>) {
+        self.mode.transform = transform;
+    }
+
+    /// Set whether underline styles other than `UnderlineStyle::Single` are
+    /// written as such.
+    ///
+    /// When disabled, every underline is written as a plain underline, for
+    /// terminals that don't support the extended underline codes.
+    ///
+    /// This is enabled by default.
+    pub fn set_styled_underlines(&mut self, yes: bool) {
+        self.mode.styled_underline = yes;
+    }
+
+    /// Set whether this writer keeps track of the terminal's state and
+    /// only writes the escape codes needed to get from one color
+    /// specification to the next.
+    ///
+    /// When enabled, `set_color` writes the same kind of minimal sequence as
+    /// `ansi::ansi_transition`. A specification with reset enabled replaces
+    /// the current state, while one with reset disabled is layered on top of
+    /// it with `ColorSpec::apply`. This can considerably shrink output that
+    /// changes styles often, such as syntax highlighted text.
+    ///
+    /// The state is only changed by `set_color` and `reset`. If escape
+    /// sequences are written to this writer by other means, then `reset`
+    /// should be called afterwards. Until the state is known, e.g., right
+    /// after this is enabled, the first specification with reset enabled is
+    /// written after a full reset.
+    ///
+    /// This is disabled by default.
+    pub fn set_minimal_transitions(&mut self, yes: bool) {
+        self.mode.minimal = yes;
+        self.mode.current = None;
+    }
+
+    /// Consume this `AnsiWriter` value and return the inner writer.
+    pub fn into_inner(self) -> W {
+        self.wtr
+    }
+
+    /// Return a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        &self.wtr
+    }
+
+    /// Return a mutable reference to the inner writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.wtr
+    }
+}
+
+impl<W: io::Write> io::Write for AnsiWriter<W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.wtr.write(buf)
+    }
+
     // Adding this method here is not required because it has a default impl,
     // but it seems to provide a perf improvement in some cases when using
     // a `BufWriter` with lots of writes.
@@ -887,16 +1359,16 @@ impl<W: io::Write> io::Write for Ansi<W> {
     // and a minimized example.
     #[inline]
     fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
-        self.0.write_all(buf)
+        self.wtr.write_all(buf)
     }
 
     #[inline]
     fn flush(&mut self) -> io::Result<()> {
-        self.0.flush()
+        self.wtr.flush()
     }
 }
 
-impl<W: io::Write> WriteColor for Ansi<W> {
+impl<W: io::Write> WriteColor for AnsiWriter<W> {
     #[inline]
     fn supports_color(&self) -> bool {
         true
@@ -907,33 +1379,18 @@ impl<W: io::Write> WriteColor for Ansi<W> {
         true
     }
 
+    #[inline]
+    fn color_level(&self) -> ColorLevel {
+        self.mode.level
+    }
+
     #[inline]
     fn set_color(&mut self, spec: &ColorSpec) -> io::Result<()> {
-        if spec.reset() {
-            self.reset()?;
-        }
-        if spec.bold() {
-            self.write_str("\x1B[1m")?;
-        }
-        if spec.dimmed() {
-            self.write_str("\x1B[2m")?;
-        }
-        if spec.italic() {
-            self.write_str("\x1B[3m")?;
-        }
-        if spec.underline() {
-            self.write_str("\x1B[4m")?;
-        }
-        if spec.strikethrough() {
-            self.write_str("\x1B[9m")?;
-        }
-        if let Some(c) = spec.fg() {
-            self.write_color(true, c, spec.intense())?;
-        }
-        if let Some(c) = spec.bg() {
-            self.write_color(false, c, spec.intense())?;
+        if self.mode.minimal {
+            self.write_transition(spec)
+        } else {
+            self.write_spec(spec)
         }
-        Ok(())
     }
 
     #[inline]
@@ -947,15 +1404,134 @@ impl<W: io::Write> WriteColor for Ansi<W> {
 
     #[inline]
     fn reset(&mut self) -> io::Result<()> {
+        if self.mode.minimal {
+            self.mode.current = Some(ColorSpec::new());
+        }
         self.write_str("\x1B[0m")
     }
 }
 
-impl<W: io::Write> Ansi<W> {
+impl<W: io::Write> AnsiWriter<W> {
     fn write_str(&mut self, s: &str) -> io::Result<()> {
         self.write_all(s.as_bytes())
     }
 
+    /// Writes every setting of the given specification.
+    fn write_spec(&mut self, spec: &ColorSpec) -> io::Result<()> {
+        if spec.reset() {
+            self.reset()?;
+        }
+        crate::ansi::ansi_attrs(
+            &mut self.wtr,
+            spec,
+            self.mode.styled_underline,
+        )?;
+        if let Some(c) = spec.fg() {
+            self.write_mode_color(true, c, spec.intense())?;
+        }
+        if let Some(c) = spec.bg() {
+            self.write_mode_color(false, c, spec.intense())?;
+        }
+        if let Some(c) = spec.underline_color()
+            && let Some((c, intense)) = self.mode_color(c, spec.intense())
+        {
+            let c = if intense { c.to_bright() } else { c };
+            crate::ansi::ansi_underline_color(&mut self.wtr, &c)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the minimal transition from the current state to the state
+    /// set by the given specification, and tracks the new state.
+    fn write_transition(&mut self, spec: &ColorSpec) -> io::Result<()> {
+        let mut target = *spec;
+        let colors = [spec.fg(), spec.bg(), spec.underline_color()];
+        let [fg, bg, ul] = colors.map(|c| {
+            let (c, intense) = self.mode_color(c?, spec.intense())?;
+            // Intense named colors are written as indices into the 256
+            // color palette, just like `write_color` does.
+            match c.ansi16_index() {
+                Some(n) if intense && n < 8 => Some(Color::Ansi256(n + 8)),
+                _ => Some(c),
+            }
+        });
+        target.set_fg(fg).set_bg(bg).set_underline_color(ul);
+        if !self.mode.styled_underline && target.underline() {
+            target.set_underline_style(Some(UnderlineStyle::Single));
+        }
+        let from = self.mode.current.take();
+        let mut next = match (spec.reset(), from.as_ref()) {
+            (true, _) => target,
+            (false, Some(from)) => {
+                let mut next = from.merge(&target);
+                // Turning an attribute off also turns off the one that
+                // shares its off code, unless that one is set too, just like
+                // when the specification is written as is.
+                for attr in Attribute::ALL {
+                    if target.attr(attr) != Some(false) {
+                        continue;
+                    }
+                    for other in Attribute::ALL {
+                        if other.off_sgr() == attr.off_sgr()
+                            && target.attr(other).is_none()
+                        {
+                            next.set_attr(other, Some(false));
+                        }
+                    }
+                }
+                next
+            }
+            // Without a known state, there's nothing to layer on top of, so
+            // write the specification as is.
+            (false, None) => return self.write_spec(spec),
+        };
+        crate::ansi::normalize_state(&mut next);
+        crate::ansi::write_transition(&mut self.wtr, from.as_ref(), &next)?;
+        self.mode.current = Some(next);
+        Ok(())
+    }
+
+    /// Writes the given color, after degrading it to this writer's color
+    /// level if degrading is enabled.
+    fn write_mode_color(
+        &mut self,
+        fg: bool,
+        c: &Color,
+        intense: bool,
+    ) -> io::Result<()> {
+        match self.mode_color(c, intense) {
+            None => Ok(()),
+            Some((c, intense)) => self.write_color(fg, &c, intense),
+        }
+    }
+
+    /// Returns the given color and intensity after applying this writer's
+    /// color transform and degrading it to this writer's color level, if
+    /// those are enabled. `None` is returned if the color is dropped.
+    fn mode_color(&self, c: &Color, intense: bool) -> Option<(Color, bool)> {
+        let c = match self.mode.transform {
+            None => *c,
+            Some(ref t) => {
+                // Intense colors are brighter versions of the named colors,
+                // so make that explicit before their values are changed.
+                let c = if intense { c.to_bright() } else { *c };
+                t.apply(&c)
+            }
+        };
+        if !self.mode.degrade {
+            return Some((c, intense));
+        }
+        let level = self.mode.level;
+        // Intense named colors are normally written as indices into the 256
+        // color palette, so below that level they become bright colors.
+        let (c, intense) = if intense && level < ColorLevel::Ansi256 {
+            (c.to_bright(), false)
+        } else {
+            (c, intense)
+        };
+        c.to_level(level).map(|c| (c, intense))
+    }
+
     fn write_color(
         &mut self,
         fg: bool,
@@ -980,6 +1556,24 @@ impl<W: io::Write> Ansi<W> {
                 }
             };
         }
+        macro_rules! write_bright {
+            ($clr:expr) => {
+                if fg {
+                    self.write_str(concat!("\x1B[9", $clr, "m"))
+                } else {
+                    self.write_str(concat!("\x1B[10", $clr, "m"))
+                }
+            };
+        }
+        macro_rules! write_default {
+            () => {
+                if fg {
+                    self.write_str("\x1B[39m")
+                } else {
+                    self.write_str("\x1B[49m")
+                }
+            };
+        }
         macro_rules! write_var_ansi_code {
             ($pre:expr, $($code:expr),+) => {{
                 // The loop generates at worst a literal of the form
@@ -1044,8 +1638,17 @@ impl<W: io::Write> Ansi<W> {
                 Color::Magenta => write_intense!("13"),
                 Color::Yellow => write_intense!("11"),
                 Color::White => write_intense!("15"),
+                Color::BrightBlack => write_bright!("0"),
+                Color::BrightBlue => write_bright!("4"),
+                Color::BrightGreen => write_bright!("2"),
+                Color::BrightRed => write_bright!("1"),
+                Color::BrightCyan => write_bright!("6"),
+                Color::BrightMagenta => write_bright!("5"),
+                Color::BrightYellow => write_bright!("3"),
+                Color::BrightWhite => write_bright!("7"),
                 Color::Ansi256(c) => write_custom!(c),
                 Color::Rgb(r, g, b) => write_custom!(r, g, b),
+                Color::Default => write_default!(),
             }
         } else {
             match *c {
@@ -1057,8 +1660,17 @@ impl<W: io::Write> Ansi<W> {
                 Color::Magenta => write_normal!("5"),
                 Color::Yellow => write_normal!("3"),
                 Color::White => write_normal!("7"),
+                Color::BrightBlack => write_bright!("0"),
+                Color::BrightBlue => write_bright!("4"),
+                Color::BrightGreen => write_bright!("2"),
+                Color::BrightRed => write_bright!("1"),
+                Color::BrightCyan => write_bright!("6"),
+                Color::BrightMagenta => write_bright!("5"),
+                Color::BrightYellow => write_bright!("3"),
+                Color::BrightWhite => write_bright!("7"),
                 Color::Ansi256(c) => write_custom!(c),
                 Color::Rgb(r, g, b) => write_custom!(r, g, b),
+                Color::Default => write_default!(),
             }
         }
     }
@@ -1117,6 +1729,10 @@ impl<W: io::Write> LossyStandardStream<W> {
     fn get_ref(&self) -> &W {
         &self.wtr
     }
+
+    fn get_mut(&mut self) -> &mut W {
+        &mut self.wtr
+    }
 }
 
 impl<W: WriteColor> WriteColor for LossyStandardStream<W> {
@@ -1126,6 +1742,9 @@ impl<W: WriteColor> WriteColor for LossyStandardStream<W> {
     fn supports_hyperlinks(&self) -> bool {
         self.wtr.supports_hyperlinks()
     }
+    fn color_level(&self) -> ColorLevel {
+        self.wtr.color_level()
+    }
     fn set_color(&mut self, spec: &ColorSpec) -> io::Result<()> {
         self.wtr.set_color(spec)
     }